@@ -0,0 +1,97 @@
+//! Compares the literal fast path against the regex engine on the same patterns.
+//!
+//! Every pattern is paired with one that matches exactly the same names but has a
+//! metacharacter (`[.]` instead of `\.`), so it still goes through the regex engine.
+//! The `names` group matches a million generated filenames shaped like a source tree.
+//! Set `SCANIT_BENCH_TREE` to a directory to also time `count_matches` over a real walk of
+//! those names. The tree is created there on the first run if the directory doesn't exist.
+//!
+//! ```text
+//! cargo bench --bench literal
+//! SCANIT_BENCH_TREE=/tmp/scanit-bench cargo bench --bench literal -- walk
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use scanit::{count_matches, CaseMode, Matcher, SearchConfig};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+const NAME_COUNT: usize = 1_000_000;
+const EXTENSIONS: &[&str] = &["rs", "c", "h", "py", "md", "txt", "toml", "log", "lock", "json"];
+
+/// `(label, literal pattern, the same pattern forced through the regex engine)`.
+const PATTERNS: &[(&str, &str, &str)] = &[
+    ("extension", r"\.rs$", r"[.]rs$"),
+    ("suffix", r"_7\.txt$", r"_7[.]txt$"),
+    ("prefix", r"^file_99", r"^file_9[9]"),
+    ("exact", r"^Cargo\.toml$", r"^Cargo[.]toml$"),
+    ("contains", r"_42_", r"_4[2]_"),
+];
+
+/// The directory and filename of every generated file, 1000 files to a directory.
+fn entries() -> impl Iterator<Item = (String, String)> {
+    (0..NAME_COUNT).map(|index| {
+        let (file, dir) = (index % 1000, index / 1000);
+        let extension = EXTENSIONS[index % EXTENSIONS.len()];
+        (format!("d{dir:03}/sub{}", dir % 4), format!("file_{file}_{dir}.{extension}"))
+    })
+}
+
+/// Writes the generated files under `root` as empty files, unless `root` already exists.
+fn create_tree(root: &Path) -> io::Result<()> {
+    if root.exists() {
+        return Ok(());
+    }
+    eprintln!("creating {NAME_COUNT} files under {}", root.display());
+    for (dir, name) in entries() {
+        let dir = root.join(dir);
+        if name.starts_with("file_0_") {
+            fs::create_dir_all(&dir)?;
+        }
+        File::create(dir.join(name))?;
+    }
+    Ok(())
+}
+
+fn config(pattern: &str, root: &str) -> SearchConfig {
+    SearchConfig::new(pattern, root, true, CaseMode::Sensitive, 4, false, false, None, false, false)
+}
+
+fn matcher(pattern: &str) -> Matcher {
+    Matcher::new(&config(pattern, ".")).expect("benchmark patterns compile")
+}
+
+fn bench_names(c: &mut Criterion) {
+    let names: Vec<Vec<u8>> = entries().map(|(_, name)| name.into_bytes()).collect();
+    let mut group = c.benchmark_group("names");
+    group.throughput(Throughput::Elements(NAME_COUNT as u64));
+    for &(label, literal, regex) in PATTERNS {
+        let (literal, regex) = (matcher(literal), matcher(regex));
+        let count = |matcher: &Matcher| names.iter().filter(|name| matcher.is_match(black_box(name))).count();
+        assert_eq!(count(&literal), count(&regex), "{label} patterns disagree");
+
+        group.bench_function(BenchmarkId::new("literal", label), |b| b.iter(|| count(&literal)));
+        group.bench_function(BenchmarkId::new("regex", label), |b| b.iter(|| count(&regex)));
+    }
+    group.finish();
+}
+
+fn bench_walk(c: &mut Criterion) {
+    let Ok(root) = std::env::var("SCANIT_BENCH_TREE") else {
+        return;
+    };
+    create_tree(root.as_ref()).expect("benchmark tree can be created");
+    let mut group = c.benchmark_group("walk");
+    group.sample_size(10).measurement_time(Duration::from_secs(20));
+    for &(label, literal, regex) in PATTERNS {
+        let (literal, regex) = (config(literal, &root), config(regex, &root));
+        group.bench_function(BenchmarkId::new("literal", label), |b| b.iter(|| count_matches(&literal).unwrap()));
+        group.bench_function(BenchmarkId::new("regex", label), |b| b.iter(|| count_matches(&regex).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_names, bench_walk);
+criterion_main!(benches);
@@ -1,14 +1,194 @@
+use crate::{ContentKind, ElfFilter, Expr, DOT_PATTERN};
+use std::borrow::Cow;
+use std::str::from_utf8;
+use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// How letter case is treated when matching the pattern.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseMode {
+    /// Case-insensitive unless the pattern contains an uppercase letter, like ripgrep's `--smart-case`.
+    #[default]
+    Smart,
+    Insensitive,
+    Sensitive,
+}
+
+impl CaseMode {
+    /// Whether `pattern` should be matched ignoring case.
+    ///
+    /// Escape sequences such as `\W` or `\p{Lu}` are not literal letters, so they never
+    /// turn off smart case.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::CaseMode;
+    ///
+    /// assert!(CaseMode::Smart.is_insensitive(r"readme\.md"));
+    /// assert!(!CaseMode::Smart.is_insensitive("README"));
+    /// assert!(CaseMode::Smart.is_insensitive(r"\Wfoo\p{Lu}"));
+    /// ```
+    #[must_use]
+    pub fn is_insensitive(self, pattern: &str) -> bool {
+        match self {
+            Self::Insensitive => true,
+            Self::Sensitive => false,
+            Self::Smart => !has_literal_uppercase(pattern),
+        }
+    }
+}
+
+fn has_literal_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(current) = chars.next() {
+        if current == '\\' {
+            // `\p{..}` and `\P{..}` name a class, skip the braces along with the escape.
+            if matches!(chars.next(), Some('p' | 'P')) && chars.clone().next() == Some('{') {
+                chars.by_ref().find(|&inner| inner == '}');
+            }
+        } else if current.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+/// The Unicode normalization form filenames and patterns are compared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition, so a decomposed `e` + U+0301 matches a typed `é`.
+    Nfc,
+    /// Compatibility composition, which also folds ligatures, full width forms and the like.
+    Nfkc,
+}
+
+impl Normalization {
+    /// Normalizes `text`, borrowing it when it is already in this form.
+    #[must_use]
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        let quick = match self {
+            Self::Nfc => is_nfc_quick(text.chars()),
+            Self::Nfkc => is_nfkc_quick(text.chars()),
+        };
+        if quick == IsNormalized::Yes {
+            return Cow::Borrowed(text);
+        }
+        let normalized: String = match self {
+            Self::Nfc => text.nfc().collect(),
+            Self::Nfkc => text.nfkc().collect(),
+        };
+        if normalized == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(normalized)
+        }
+    }
+
+    /// Normalizes a regex or glob `pattern` without changing its syntax: only the non-ASCII
+    /// parts are normalized, each with the letter or digit before it, and whatever they turn
+    /// into goes through `escape`. NFKC would otherwise make a full width `．` a live `.`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::Normalization;
+    ///
+    /// assert_eq!(Normalization::Nfkc.apply_pattern(r"^a．b\.txt$", regex::escape), r"^a\.b\.txt$");
+    /// assert_eq!(Normalization::Nfc.apply_pattern("cafe\u{301}.*", regex::escape), "caf\u{e9}.*");
+    /// ```
+    #[must_use]
+    pub fn apply_pattern(self, pattern: &str, escape: fn(&str) -> String) -> Cow<'_, str> {
+        if pattern.is_ascii() {
+            return Cow::Borrowed(pattern);
+        }
+        let mut normalized = String::with_capacity(pattern.len());
+        let mut rest = pattern;
+        while let Some(found) = rest.find(|c: char| !c.is_ascii()) {
+            // A combining mark can compose with the letter before it.
+            let start = if rest.as_bytes()[..found].last().is_some_and(u8::is_ascii_alphanumeric) {
+                found - 1
+            } else {
+                found
+            };
+            let end = rest[found..].find(|c: char| c.is_ascii()).map_or(rest.len(), |end| found + end);
+            normalized.push_str(&rest[..start]);
+            normalized.push_str(&escape(&self.apply(&rest[start..end])));
+            rest = &rest[end..];
+        }
+        normalized.push_str(rest);
+        if normalized == pattern {
+            Cow::Borrowed(pattern)
+        } else {
+            Cow::Owned(normalized)
+        }
+    }
+
+    /// Normalizes a filename or path. ASCII and invalid UTF-8 are returned as they are, so
+    /// names that aren't UTF-8 are still matched byte for byte.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::Normalization;
+    ///
+    /// let decomposed = "cafe\u{301}.txt".as_bytes();
+    /// assert_eq!(&*Normalization::Nfc.apply_bytes(decomposed), "café.txt".as_bytes());
+    /// assert_eq!(&*Normalization::Nfkc.apply_bytes("ﬁle".as_bytes()), b"file");
+    /// assert_eq!(&*Normalization::Nfc.apply_bytes(b"caf\xe9"), b"caf\xe9");
+    /// ```
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn apply_bytes(self, bytes: &[u8]) -> Cow<'_, [u8]> {
+        if bytes.is_ascii() {
+            return Cow::Borrowed(bytes);
+        }
+        match from_utf8(bytes).map(|text| self.apply(text)) {
+            Ok(Cow::Owned(normalized)) => Cow::Owned(normalized.into_bytes()),
+            Ok(Cow::Borrowed(_)) | Err(_) => Cow::Borrowed(bytes),
+        }
+    }
+}
+
+/// Everything a search is configured with.
+///
+/// Build one with [`SearchConfig::new`] and set the optional fields afterwards, or with a struct
+/// literal ending in `..SearchConfig::default()`, so that code keeps compiling as fields are added.
+#[derive(Debug, Clone)]
 pub struct SearchConfig {
     pub pattern: String,
     pub root: String,
     pub hide_hidden: bool,
-    pub case_sensitive: bool,
+    pub case_mode: CaseMode,
     pub thread_count: usize,
     pub keep_dirs: bool,
     pub keep_sys_paths: bool,
     pub max_depth: Option<usize>,
     pub use_glob: bool,
     pub full_path: bool,
+    /// More patterns searched alongside `pattern`, any of them matching is enough.
+    pub extra_patterns: Vec<String>,
+    /// Require `pattern` and every extra pattern to match instead of any one of them.
+    pub match_all: bool,
+    /// Paths matching any of these are left out.
+    pub exclude_patterns: Vec<String>,
+    /// A `--where` expression every match also has to pass.
+    pub filter: Option<Expr>,
+    /// Match the patterns against directory names below the root and return everything under
+    /// a matching directory, instead of matching each entry itself.
+    pub match_dir: bool,
+    /// The immediate parent directory's name has to match this pattern.
+    pub parent_pattern: Option<String>,
+    /// Compare filenames and patterns in this normalization form. Paths are still returned as
+    /// their raw bytes.
+    pub normalize: Option<Normalization>,
+    /// Only keep regular files whose contents match this regex, see [`crate::ContentMatcher`].
+    pub contains: Option<String>,
+    /// Files larger than this many bytes are not searched by `contains`.
+    pub max_filesize: Option<u64>,
+    /// Only keep binary or only text files, judged by their first few KiB.
+    pub content_kind: Option<ContentKind>,
+    /// Only keep files whose sniffed MIME type is one of these, `image/*` style wildcards allowed.
+    pub mime_types: Vec<String>,
+    /// Only keep ELF files with these properties, read from their headers.
+    pub elf: Option<ElfFilter>,
 }
 
 impl SearchConfig {
@@ -17,7 +197,7 @@ impl SearchConfig {
         pattern: &str,
         root: &str,
         hide_hidden: bool,
-        case_sensitive: bool,
+        case_mode: CaseMode,
         thread_count: usize,
         keep_dirs: bool,
         keep_sys_paths: bool,
@@ -29,13 +209,33 @@ impl SearchConfig {
             pattern: pattern.into(),
             root: root.into(),
             hide_hidden,
-            case_sensitive,
+            case_mode,
             thread_count,
             keep_dirs,
             keep_sys_paths,
             max_depth,
             use_glob,
             full_path,
+            extra_patterns: Vec::new(),
+            match_all: false,
+            exclude_patterns: Vec::new(),
+            filter: None,
+            match_dir: false,
+            parent_pattern: None,
+            normalize: None,
+            contains: None,
+            max_filesize: None,
+            content_kind: None,
+            mime_types: Vec::new(),
+            elf: None,
         }
     }
 }
+
+impl Default for SearchConfig {
+    /// Every non-hidden file under the current directory, on every available thread.
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        Self::new(DOT_PATTERN, ".", true, CaseMode::Smart, threads, false, false, None, false, false)
+    }
+}
@@ -1,5 +1,10 @@
 pub const DOT_PATTERN: &str = ".";
 
+/// How much of a file is read to sniff its format, and checked for a NUL byte to tell binary
+/// files from text.
+#[allow(unused)]
+pub const SNIFF_LEN: usize = 8 * 1024;
+
 #[cfg(unix)]
 #[allow(unused)]
 pub const START_PREFIX: &str = "/";
@@ -0,0 +1,192 @@
+use crate::{
+    build_regex, build_walker, compile_pattern, entry_matcher, unbounded, AsBytes, BoxBytes, CaseMode, FileHead, Receiver,
+    ScanError, SearchConfig, Sniffer,
+};
+use ignore::{DirEntry, WalkState};
+use memchr::{memchr, memchr_iter};
+use memmap2::Mmap;
+use regex::bytes::Regex;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread::spawn;
+
+/// Files bigger than this are memory mapped instead of read.
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// A `--contains` regex, run over file contents in the worker threads.
+///
+/// Files with a NUL byte in their first 8 KiB are taken to be binary and never match, and
+/// `max_filesize` leaves out files too big to be worth searching.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{CaseMode, ContentMatcher, ScanError};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let contains = ContentMatcher::new(r"^\[package\]", CaseMode::Smart, None)?;
+///     assert_eq!(contains.first_match_line("Cargo.toml".as_ref()), Some(1));
+///     assert_eq!(contains.first_match_line("src/lib.rs".as_ref()), None);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContentMatcher {
+    regex: Regex,
+    max_filesize: Option<u64>,
+}
+
+impl ContentMatcher {
+    /// Compiles `pattern`, with `^` and `$` matching at line boundaries.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ScanError` if the regex fails to compile.
+    pub fn new(pattern: &str, case_mode: CaseMode, max_filesize: Option<u64>) -> Result<Self, ScanError> {
+        Ok(Self {
+            regex: build_regex(&format!("(?m){pattern}"), case_mode.is_insensitive(pattern))?,
+            max_filesize,
+        })
+    }
+
+    /// The 1-based line of the first match in the file at `path`. `None` when nothing matched
+    /// or the file is binary, larger than `max_filesize` or can't be read.
+    #[must_use]
+    pub fn first_match_line(&self, path: &Path) -> Option<u64> {
+        self.first_match_line_in(&FileHead::open(path).ok()?)
+    }
+
+    /// [`ContentMatcher::first_match_line`] for a file that is already open.
+    ///
+    /// Files up to 1 MiB are read into memory, larger ones are memory mapped, so a worker
+    /// never holds more than that of a file on its heap.
+    #[must_use]
+    pub fn first_match_line_in(&self, file: &FileHead) -> Option<u64> {
+        if self.max_filesize.is_some_and(|max_filesize| file.len() > max_filesize) || memchr(0, file.head()).is_some() {
+            return None;
+        }
+        if file.is_complete() {
+            return self.line_of(file.head());
+        }
+        if file.len() > MMAP_THRESHOLD {
+            // SAFETY: the map is only read while this call runs. A file truncated meanwhile
+            // can fault, the same trade-off grep tools make for large files.
+            let map = unsafe { Mmap::map(file.file()) }.ok()?;
+            return self.line_of(&map);
+        }
+
+        let mut contents = Vec::with_capacity(usize::try_from(file.len()).unwrap_or(0));
+        contents.extend_from_slice(file.head());
+        let mut rest = file.file();
+        rest.seek(SeekFrom::Start(file.head().len() as u64)).ok()?;
+        rest.read_to_end(&mut contents).ok()?;
+        self.line_of(&contents)
+    }
+
+    fn line_of(&self, contents: &[u8]) -> Option<u64> {
+        let found = self.regex.find(contents)?;
+        let newlines = memchr_iter(b'\n', &contents[..found.start()]).count();
+        Some(u64::try_from(newlines).unwrap_or(u64::MAX) + 1)
+    }
+
+    /// Whether `entry` is a regular file whose contents match.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn is_match(&self, entry: &DirEntry) -> bool {
+        entry.file_type().is_some_and(|filetype| filetype.is_file()) && self.first_match_line(entry.path()).is_some()
+    }
+
+    /// [`ContentMatcher::is_match`] for a regular file that is already open.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn is_head_match(&self, file: &FileHead) -> bool {
+        self.first_match_line_in(file).is_some()
+    }
+}
+
+/// A file whose contents matched, with the line of the first match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMatch {
+    pub path: BoxBytes,
+    pub line: u64,
+}
+
+/// Like [`crate::find_files_iter`] with [`SearchConfig::contains`] set, but also gives the line
+/// of the first match in each file.
+///
+/// Each file is opened once for the content search and any [`SearchConfig::content_kind`],
+/// [`SearchConfig::mime_types`] or [`SearchConfig::elf`] filter.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if a pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_content_matches, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let mut search_config = SearchConfig::new(r"\.rs$", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///     search_config.contains = Some("fn find_content_matches".into());
+///
+///     let found: Vec<_> = find_content_matches(&search_config)?.iter().collect();
+///     assert!(found.iter().any(|found| found.path.ends_with(b"content.rs") && found.line > 1));
+///     Ok(())
+/// }
+/// ```
+pub fn find_content_matches(search_config: &SearchConfig) -> Result<Receiver<ContentMatch>, ScanError> {
+    let contains = ContentMatcher::new(
+        search_config
+            .contains
+            .as_deref()
+            .ok_or_else(|| ScanError::Other("find_content_matches needs SearchConfig::contains".into()))?,
+        search_config.case_mode,
+        search_config.max_filesize,
+    )?;
+    let sniffer = Sniffer::new(search_config.content_kind, &search_config.mime_types);
+    let elf = search_config.elf.clone();
+    // Paths are matched without the filters that read the file, which share one open below.
+    let search_config = SearchConfig {
+        contains: None,
+        content_kind: None,
+        mime_types: Vec::new(),
+        elf: None,
+        ..search_config.clone()
+    };
+    let re = compile_pattern(&search_config)?;
+    let (tx, rx) = unbounded::<ContentMatch>();
+
+    spawn(move || {
+        let is_match = entry_matcher(&search_config);
+        build_walker(&search_config, re.as_ref(), None).run(|| {
+            let re = re.as_ref();
+            let (contains, sniffer, elf) = (&contains, &sniffer, &elf);
+            let tx = tx.clone();
+            Box::new(move |entry| {
+                let Ok(entry_path) = entry else {
+                    return WalkState::Continue;
+                };
+                if !entry_path.file_type().is_some_and(|filetype| filetype.is_file()) || !is_match(&entry_path, re) {
+                    return WalkState::Continue;
+                }
+                let Ok(file) = FileHead::open(entry_path.path()) else {
+                    return WalkState::Continue;
+                };
+                if !sniffer.as_ref().map_or(true, |sniffer| sniffer.is_head_match(&file))
+                    || !elf.as_ref().map_or(true, |elf| elf.is_head_match(&file))
+                {
+                    return WalkState::Continue;
+                }
+                contains.first_match_line_in(&file).map_or(WalkState::Continue, |line| {
+                    tx.send(ContentMatch {
+                        path: entry_path.as_true_bytes().into(),
+                        line,
+                    })
+                    .map_or(WalkState::Quit, |()| WalkState::Continue)
+                })
+            })
+        });
+    });
+    Ok(rx)
+}
@@ -0,0 +1,136 @@
+use crate::{build_walker, compile_pattern, entry_matcher, AsBytes, BoxBytes, ScanError, SearchConfig};
+use ignore::{DirEntry, WalkState};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Mutex;
+
+/// Number of matches directly inside one directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirCount {
+    pub path: BoxBytes,
+    pub count: u64,
+}
+
+type Counts = HashMap<BoxBytes, u64>;
+
+/// Counts owned by one worker thread, merged into `merged` when the worker finishes.
+struct LocalCounts<'a> {
+    counts: Counts,
+    merged: &'a Mutex<Counts>,
+}
+
+impl Drop for LocalCounts<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            for (dir, count) in self.counts.drain() {
+                *merged.entry(dir).or_insert(0) += count;
+            }
+        }
+    }
+}
+
+/// Same filter as the path search: directories only count with `keep_dirs`.
+fn is_counted(entry: &DirEntry, keep_dirs: bool) -> bool {
+    keep_dirs || !entry.file_type().is_some_and(|filetype| filetype.is_dir())
+}
+
+/// Counts the entries [`crate::find_files_iter`] would return, without collecting or sending
+/// any paths.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{count_matches, find_files_iter, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(r"\.rs$", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     let count = count_matches(&search_config)?;
+///     assert_eq!(count, find_files_iter(&search_config)?.iter().count() as u64);
+///     Ok(())
+/// }
+/// ```
+pub fn count_matches(search_config: &SearchConfig) -> Result<u64, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let keep_dirs = search_config.keep_dirs;
+    let count = AtomicU64::new(0);
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let re = re.as_ref();
+        let count = &count;
+        Box::new(move |entry| {
+            if let Ok(entry_path) = entry {
+                if is_counted(&entry_path, keep_dirs) && is_match(&entry_path, re) {
+                    count.fetch_add(1, AtomicOrdering::Relaxed);
+                }
+            }
+            WalkState::Continue
+        })
+    });
+
+    Ok(count.into_inner())
+}
+
+/// Like [`count_matches`], but split by the directory each match is in, most matches first.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{count_by_dir, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(r"\.rs$", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     for dir in count_by_dir(&search_config)? {
+///         println!("{} {:?}", dir.count, &*dir.path);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn count_by_dir(search_config: &SearchConfig) -> Result<Vec<DirCount>, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let keep_dirs = search_config.keep_dirs;
+    let merged: Mutex<Counts> = Mutex::new(HashMap::new());
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let mut local = LocalCounts {
+            counts: HashMap::new(),
+            merged: &merged,
+        };
+        let re = re.as_ref();
+        Box::new(move |entry| {
+            if let Ok(entry_path) = entry {
+                if is_counted(&entry_path, keep_dirs) && is_match(&entry_path, re) {
+                    let parent = entry_path
+                        .path()
+                        .parent()
+                        .map_or_else(Default::default, |parent| parent.as_os_str().as_true_bytes().into());
+                    *local.counts.entry(parent).or_insert(0) += 1;
+                }
+            }
+            WalkState::Continue
+        })
+    });
+
+    let mut counts: Vec<DirCount> = merged
+        .into_inner()
+        .map_err(|_| ScanError::Other("a worker thread panicked".into()))?
+        .into_iter()
+        .map(|(path, count)| DirCount { path, count })
+        .collect();
+    counts.sort_unstable_by(|left, right| {
+        right
+            .count
+            .cmp(&left.count)
+            .then_with(|| left.path.cmp(&right.path))
+    });
+    Ok(counts)
+}
@@ -0,0 +1,165 @@
+use crate::{build_walker, compile_pattern, entry_matcher, AsBytes, BoxBytes, ScanError, SearchConfig};
+use ignore::WalkState;
+use std::collections::{HashMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Space used by a directory and everything below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirUsage {
+    pub path: BoxBytes,
+    /// Depth below the search root, the root itself is 0.
+    pub depth: usize,
+    /// Sum of file lengths.
+    pub apparent: u64,
+    /// Space allocated on disk (`st_blocks * 512` on unix).
+    pub allocated: u64,
+}
+
+type Sizes = HashMap<PathBuf, (u64, u64)>;
+
+#[cfg(unix)]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Returns true the first time a multiply linked file is seen, so hard links are only counted once.
+#[cfg(unix)]
+fn first_link(metadata: &Metadata, seen: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() <= 1 || metadata.is_dir() {
+        return true;
+    }
+    seen.lock()
+        .map_or(true, |mut seen| seen.insert((metadata.dev(), metadata.ino())))
+}
+
+#[cfg(not(unix))]
+const fn first_link(_metadata: &Metadata, _seen: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    true
+}
+
+/// Sizes owned by one worker thread, merged into `merged` when the worker finishes.
+struct LocalSizes<'a> {
+    sizes: Sizes,
+    merged: &'a Mutex<Sizes>,
+}
+
+impl Drop for LocalSizes<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            for (dir, (apparent, allocated)) in self.sizes.drain() {
+                let total = merged.entry(dir).or_insert((0, 0));
+                total.0 += apparent;
+                total.1 += allocated;
+            }
+        }
+    }
+}
+
+/// Adds up the apparent and allocated size of every directory under `search_config.root`.
+///
+/// Each total includes all subdirectories. Only files matching the search pattern are counted
+/// (use `"."` to count everything), directories themselves are always counted, and files with
+/// several hard links are counted once by `(dev, inode)`. `max_depth` limits the walk itself,
+/// so to only show shallow directories filter the result on [`DirUsage::depth`] instead.
+///
+/// The result is sorted by allocated size, heaviest first.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{disk_usage, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(".", "src", false, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     let usage = disk_usage(&search_config)?;
+///     assert!(usage.iter().any(|dir| dir.depth == 0));
+///     for dir in usage.iter().filter(|dir| dir.depth <= 1) {
+///         println!("{} {:?}", dir.allocated, &*dir.path);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn disk_usage(search_config: &SearchConfig) -> Result<Vec<DirUsage>, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let merged: Mutex<Sizes> = Mutex::new(HashMap::new());
+    let seen_links: Mutex<HashSet<(u64, u64)>> = Mutex::new(HashSet::new());
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let mut local = LocalSizes {
+            sizes: HashMap::new(),
+            merged: &merged,
+        };
+        let re = re.as_ref();
+        let seen_links = &seen_links;
+        Box::new(move |entry| {
+            entry.map_or(WalkState::Continue, |entry_path| {
+                let Ok(metadata) = entry_path.metadata() else {
+                    return WalkState::Continue;
+                };
+
+                let owner = if metadata.is_dir() {
+                    entry_path.path()
+                } else if is_match(&entry_path, re) && first_link(&metadata, seen_links) {
+                    match entry_path.path().parent() {
+                        Some(parent) => parent,
+                        None => return WalkState::Continue,
+                    }
+                } else {
+                    return WalkState::Continue;
+                };
+
+                let total = local.sizes.entry(owner.to_path_buf()).or_insert((0, 0));
+                total.0 += metadata.len();
+                total.1 += allocated_size(&metadata);
+                WalkState::Continue
+            })
+        })
+    });
+
+    let direct = merged
+        .into_inner()
+        .map_err(|_| ScanError::Other("a worker thread panicked".into()))?;
+
+    let root = Path::new(&search_config.root);
+    let mut totals: Sizes = HashMap::with_capacity(direct.len());
+    for (dir, (apparent, allocated)) in direct {
+        let mut current = Some(dir.as_path());
+        while let Some(ancestor) = current.filter(|ancestor| ancestor.starts_with(root)) {
+            let total = totals.entry(ancestor.to_path_buf()).or_insert((0, 0));
+            total.0 += apparent;
+            total.1 += allocated;
+            current = ancestor.parent();
+        }
+    }
+
+    let mut usage: Vec<DirUsage> = totals
+        .into_iter()
+        .map(|(dir, (apparent, allocated))| DirUsage {
+            depth: dir.strip_prefix(root).map_or(0, |relative| relative.components().count()),
+            path: dir.as_os_str().as_true_bytes().into(),
+            apparent,
+            allocated,
+        })
+        .collect();
+    usage.sort_unstable_by(|left, right| {
+        right
+            .allocated
+            .cmp(&left.allocated)
+            .then_with(|| left.path.cmp(&right.path))
+    });
+    Ok(usage)
+}
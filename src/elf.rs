@@ -0,0 +1,435 @@
+use crate::FileHead;
+use ignore::DirEntry;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const SHT_SYMTAB: u32 = 2;
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const EM_X86_64: u16 = 62;
+const EM_AARCH64: u16 = 183;
+/// Tables bigger than this are taken to be corrupt rather than read.
+const MAX_TABLE_LEN: u64 = 16 * 1024 * 1024;
+
+/// The CPU architectures the ELF filters know by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfArch {
+    X86_64,
+    Aarch64,
+}
+
+/// How an ELF file is linked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    Static,
+    /// Has a program interpreter or needs shared libraries.
+    Dynamic,
+}
+
+/// What [`ElfInfo::read`] found in an ELF file's headers and dynamic section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfInfo {
+    /// The raw `e_machine` value.
+    pub machine: u16,
+    pub linkage: Linkage,
+    /// No `.symtab` section is left.
+    pub stripped: bool,
+    /// The `DT_NEEDED` entries, e.g. `libssl.so.1.1`.
+    pub needed: Vec<String>,
+}
+
+/// Reads the fields of one ELF class and byte order out of a buffer.
+#[derive(Clone, Copy)]
+struct Layout {
+    is_64: bool,
+    little_endian: bool,
+}
+
+impl Layout {
+    fn bytes<const N: usize>(bytes: &[u8], offset: usize) -> Option<[u8; N]> {
+        bytes.get(offset..offset.checked_add(N)?)?.try_into().ok()
+    }
+
+    fn u16(self, bytes: &[u8], offset: usize) -> Option<u16> {
+        let raw = Self::bytes(bytes, offset)?;
+        Some(if self.little_endian { u16::from_le_bytes(raw) } else { u16::from_be_bytes(raw) })
+    }
+
+    fn u32(self, bytes: &[u8], offset: usize) -> Option<u32> {
+        let raw = Self::bytes(bytes, offset)?;
+        Some(if self.little_endian { u32::from_le_bytes(raw) } else { u32::from_be_bytes(raw) })
+    }
+
+    fn u64(self, bytes: &[u8], offset: usize) -> Option<u64> {
+        let raw = Self::bytes(bytes, offset)?;
+        Some(if self.little_endian { u64::from_le_bytes(raw) } else { u64::from_be_bytes(raw) })
+    }
+
+    /// A word that is 8 bytes in 64-bit files and 4 bytes in 32-bit ones.
+    fn word(self, bytes: &[u8], offset: usize) -> Option<u64> {
+        if self.is_64 {
+            self.u64(bytes, offset)
+        } else {
+            self.u32(bytes, offset).map(u64::from)
+        }
+    }
+
+    /// Picks the offset of a field for this class.
+    const fn pick(self, offset_64: usize, offset_32: usize) -> usize {
+        if self.is_64 {
+            offset_64
+        } else {
+            offset_32
+        }
+    }
+}
+
+/// A program header, reduced to what the filters need.
+struct Segment {
+    kind: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+}
+
+fn read_at(file: &mut (impl Read + Seek), offset: u64, len: u64) -> Option<Vec<u8>> {
+    if len > MAX_TABLE_LEN {
+        return None;
+    }
+    let mut buffer = vec![0; usize::try_from(len).ok()?];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+/// Reads `count` table entries of `size` bytes each, starting at `offset`.
+fn read_table(file: &mut (impl Read + Seek), offset: u64, size: u16, count: u16) -> Option<Vec<u8>> {
+    read_at(file, offset, u64::from(size) * u64::from(count))
+}
+
+impl ElfInfo {
+    /// Parses the ELF header, program headers, section headers and dynamic section of the file
+    /// at `path`, reading only those parts. `None` when it isn't an ELF file or is malformed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::{ElfInfo, Linkage};
+    ///
+    /// assert_eq!(ElfInfo::read("Cargo.toml".as_ref()), None);
+    /// if let Some(info) = ElfInfo::read("/bin/sh".as_ref()) {
+    ///     if info.linkage == Linkage::Dynamic {
+    ///         println!("/bin/sh needs {:?}", info.needed);
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::parse(&mut File::open(path).ok()?)
+    }
+
+    /// [`ElfInfo::read`] for a file that is already open.
+    #[must_use]
+    pub fn from_head(file: &FileHead) -> Option<Self> {
+        if file.head().starts_with(ELF_MAGIC) {
+            Self::parse(&mut file.file())
+        } else {
+            None
+        }
+    }
+
+    fn parse(file: &mut (impl Read + Seek)) -> Option<Self> {
+        let mut header = Vec::with_capacity(64);
+        file.seek(SeekFrom::Start(0)).ok()?;
+        file.by_ref().take(64).read_to_end(&mut header).ok()?;
+        let header = &header[..];
+        if !header.starts_with(ELF_MAGIC) {
+            return None;
+        }
+        let layout = Layout {
+            is_64: *header.get(4)? == 2,
+            little_endian: *header.get(5)? == 1,
+        };
+
+        let machine = layout.u16(header, 18)?;
+        let program_offset = layout.word(header, layout.pick(32, 28))?;
+        let section_offset = layout.word(header, layout.pick(40, 32))?;
+        let program_size = layout.u16(header, layout.pick(54, 42))?;
+        let program_count = layout.u16(header, layout.pick(56, 44))?;
+        let section_size = layout.u16(header, layout.pick(58, 46))?;
+        let section_count = layout.u16(header, layout.pick(60, 48))?;
+
+        let programs = read_table(file, program_offset, program_size, program_count)?;
+        let segments: Vec<Segment> = programs
+            .chunks_exact(usize::from(program_size).max(1))
+            .map(|program| {
+                Some(Segment {
+                    kind: layout.u32(program, 0)?,
+                    offset: layout.word(program, layout.pick(8, 4))?,
+                    vaddr: layout.word(program, layout.pick(16, 8))?,
+                    filesz: layout.word(program, layout.pick(32, 16))?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        let stripped = section_count == 0
+            || !read_table(file, section_offset, section_size, section_count)?
+                .chunks_exact(usize::from(section_size).max(1))
+                .any(|section| layout.u32(section, 4) == Some(SHT_SYMTAB));
+
+        let needed = segments
+            .iter()
+            .find(|segment| segment.kind == PT_DYNAMIC)
+            .map_or_else(|| Some(Vec::new()), |dynamic| needed_libraries(file, layout, &segments, dynamic))?;
+        let linkage = if needed.is_empty() && !segments.iter().any(|segment| segment.kind == PT_INTERP) {
+            Linkage::Static
+        } else {
+            Linkage::Dynamic
+        };
+
+        Some(Self {
+            machine,
+            linkage,
+            stripped,
+            needed,
+        })
+    }
+
+    /// The architecture, if it is one the filters know.
+    #[must_use]
+    pub const fn arch(&self) -> Option<ElfArch> {
+        match self.machine {
+            EM_X86_64 => Some(ElfArch::X86_64),
+            EM_AARCH64 => Some(ElfArch::Aarch64),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the `DT_NEEDED` names from the dynamic segment, looking them up in `DT_STRTAB`.
+fn needed_libraries(file: &mut (impl Read + Seek), layout: Layout, segments: &[Segment], dynamic: &Segment) -> Option<Vec<String>> {
+    let entries = read_at(file, dynamic.offset, dynamic.filesz)?;
+    let entry_size = layout.pick(16, 8);
+    let mut needed_offsets = Vec::new();
+    let (mut string_table, mut string_table_len) = (None, None);
+    for entry in entries.chunks_exact(entry_size) {
+        let tag = layout.word(entry, 0)?;
+        let value = layout.word(entry, entry_size / 2)?;
+        match tag {
+            DT_NULL => break,
+            DT_NEEDED => needed_offsets.push(value),
+            DT_STRTAB => string_table = Some(value),
+            DT_STRSZ => string_table_len = Some(value),
+            _ => {}
+        }
+    }
+    if needed_offsets.is_empty() {
+        return Some(Vec::new());
+    }
+
+    // DT_STRTAB is an address, found in the file through the segment loading it.
+    let address = string_table?;
+    let segment = segments.iter().find(|segment| {
+        segment.kind == PT_LOAD && segment.vaddr <= address && address - segment.vaddr < segment.filesz
+    })?;
+    // Offsets come straight from the file, so a crafted one must not overflow.
+    let table_offset = segment.offset.checked_add(address - segment.vaddr)?;
+    let table_len = string_table_len.unwrap_or(segment.filesz - (address - segment.vaddr));
+    let table = read_at(file, table_offset, table_len)?;
+
+    needed_offsets
+        .into_iter()
+        .map(|offset| {
+            let name = table.get(usize::try_from(offset).ok()?..)?;
+            let end = memchr::memchr(0, name)?;
+            Some(String::from_utf8_lossy(&name[..end]).into_owned())
+        })
+        .collect()
+}
+
+/// The `--elf` and `--needs` filters. Every field left unset accepts any ELF file.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{ElfFilter, ElfInfo, Linkage};
+///
+/// let filter = ElfFilter {
+///     linkage: Some(Linkage::Dynamic),
+///     needs: vec!["libssl.so.1.1".into()],
+///     ..ElfFilter::default()
+/// };
+/// let info = ElfInfo { machine: 62, linkage: Linkage::Dynamic, stripped: true, needed: vec!["libssl.so.1.1".into(), "libc.so.6".into()] };
+/// assert!(filter.matches(&info));
+/// assert!(!filter.matches(&ElfInfo { needed: vec!["libssl.so.3".into()], ..info }));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElfFilter {
+    pub arch: Option<ElfArch>,
+    pub linkage: Option<Linkage>,
+    pub stripped: Option<bool>,
+    /// Libraries that all have to be among the `DT_NEEDED` entries.
+    pub needs: Vec<String>,
+}
+
+impl ElfFilter {
+    /// Whether `info` passes every filter that is set.
+    #[must_use]
+    pub fn matches(&self, info: &ElfInfo) -> bool {
+        self.arch.map_or(true, |arch| info.arch() == Some(arch))
+            && self.linkage.map_or(true, |linkage| info.linkage == linkage)
+            && self.stripped.map_or(true, |stripped| info.stripped == stripped)
+            && self.needs.iter().all(|library| info.needed.contains(library))
+    }
+
+    /// Whether `entry` is a regular ELF file passing the filters.
+    #[must_use]
+    pub fn is_match(&self, entry: &DirEntry) -> bool {
+        entry.file_type().is_some_and(|filetype| filetype.is_file())
+            && ElfInfo::read(entry.path()).is_some_and(|info| self.matches(&info))
+    }
+
+    /// [`ElfFilter::is_match`] for a regular file that is already open.
+    #[must_use]
+    pub fn is_head_match(&self, file: &FileHead) -> bool {
+        ElfInfo::from_head(file).is_some_and(|info| self.matches(&info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const LOAD_ADDRESS: u64 = 0x40_0000;
+    const DYNAMIC_OFFSET: usize = 0x100;
+    const STRINGS_OFFSET: usize = 0x200;
+    const SECTIONS_OFFSET: usize = 0x300;
+    const STRINGS: &[u8] = b"\0libssl.so.1.1\0libc.so.6\0";
+
+    fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+        image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// A little endian `x86_64` executable with a `PT_LOAD` covering the whole file, a
+    /// `PT_DYNAMIC` needing two libraries and a null section, plus `.symtab` when `symtab`.
+    fn elf64(symtab: bool, load_offset: u64) -> Vec<u8> {
+        let section_count: u16 = if symtab { 2 } else { 1 };
+        let mut image = vec![0; SECTIONS_OFFSET + 64 * usize::from(section_count)];
+        put(&mut image, 0, b"\x7fELF\x02\x01\x01");
+        put(&mut image, 18, &EM_X86_64.to_le_bytes());
+        put(&mut image, 32, &64_u64.to_le_bytes());
+        put(&mut image, 40, &(SECTIONS_OFFSET as u64).to_le_bytes());
+        put(&mut image, 54, &56_u16.to_le_bytes());
+        put(&mut image, 56, &2_u16.to_le_bytes());
+        put(&mut image, 58, &64_u16.to_le_bytes());
+        put(&mut image, 60, &section_count.to_le_bytes());
+
+        let segments = [
+            (PT_LOAD, load_offset, LOAD_ADDRESS, image.len() as u64),
+            (PT_DYNAMIC, DYNAMIC_OFFSET as u64, LOAD_ADDRESS + DYNAMIC_OFFSET as u64, 5 * 16),
+        ];
+        for (index, (kind, offset, vaddr, filesz)) in segments.into_iter().enumerate() {
+            let at = 64 + 56 * index;
+            put(&mut image, at, &kind.to_le_bytes());
+            put(&mut image, at + 8, &offset.to_le_bytes());
+            put(&mut image, at + 16, &vaddr.to_le_bytes());
+            put(&mut image, at + 32, &filesz.to_le_bytes());
+        }
+
+        let dynamic = [
+            (DT_NEEDED, 1),
+            (DT_NEEDED, 15),
+            (DT_STRTAB, LOAD_ADDRESS + STRINGS_OFFSET as u64),
+            (DT_STRSZ, STRINGS.len() as u64),
+            (DT_NULL, 0),
+        ];
+        for (index, (tag, value)) in dynamic.into_iter().enumerate() {
+            put(&mut image, DYNAMIC_OFFSET + 16 * index, &tag.to_le_bytes());
+            put(&mut image, DYNAMIC_OFFSET + 16 * index + 8, &value.to_le_bytes());
+        }
+        put(&mut image, STRINGS_OFFSET, STRINGS);
+        if symtab {
+            put(&mut image, SECTIONS_OFFSET + 64 + 4, &SHT_SYMTAB.to_le_bytes());
+        }
+        image
+    }
+
+    fn parse(image: &[u8]) -> Option<ElfInfo> {
+        ElfInfo::parse(&mut Cursor::new(image))
+    }
+
+    #[test]
+    fn reads_dynamic_executable() {
+        let info = parse(&elf64(false, 0)).expect("valid image parses");
+        assert_eq!(info.arch(), Some(ElfArch::X86_64));
+        assert_eq!(info.linkage, Linkage::Dynamic);
+        assert!(info.stripped);
+        assert_eq!(info.needed, ["libssl.so.1.1", "libc.so.6"]);
+        assert!(!parse(&elf64(true, 0)).expect("valid image parses").stripped);
+    }
+
+    #[test]
+    fn reads_big_endian_32_bit_header() {
+        let mut image = vec![0; 52];
+        put(&mut image, 0, b"\x7fELF\x01\x02\x01");
+        put(&mut image, 18, &8_u16.to_be_bytes());
+        let info = parse(&image).expect("header without tables parses");
+        assert_eq!((info.machine, info.arch(), info.linkage), (8, None, Linkage::Static));
+        assert!(info.stripped && info.needed.is_empty());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(parse(b""), None);
+        assert_eq!(parse(b"\x7fEL"), None);
+        assert_eq!(parse(b"#!/bin/sh\necho \x7fELF\n"), None);
+    }
+
+    #[test]
+    fn truncated_images_never_panic() {
+        let image = elf64(true, 0);
+        for len in 0..image.len() {
+            let info = parse(&image[..len]);
+            assert!(info.is_none() || len >= STRINGS_OFFSET, "parsed {len} bytes");
+        }
+    }
+
+    #[test]
+    fn overflowing_segment_offset_is_malformed() {
+        assert_eq!(parse(&elf64(false, u64::MAX)), None);
+        assert_eq!(parse(&elf64(false, u64::MAX - 0x100)), None);
+    }
+
+    #[test]
+    fn oversized_tables_are_not_read() {
+        let mut image = elf64(false, 0);
+        put(&mut image, 54, &u16::MAX.to_le_bytes());
+        put(&mut image, 56, &u16::MAX.to_le_bytes());
+        assert_eq!(parse(&image), None);
+
+        let mut image = elf64(false, 0);
+        put(&mut image, 40, &u64::MAX.to_le_bytes());
+        assert_eq!(parse(&image), None);
+    }
+
+    #[test]
+    fn bad_dynamic_entries_are_malformed() {
+        let mut image = elf64(false, 0);
+        put(&mut image, DYNAMIC_OFFSET + 8, &u64::MAX.to_le_bytes());
+        assert_eq!(parse(&image), None, "DT_NEEDED past the string table");
+
+        let mut image = elf64(false, 0);
+        put(&mut image, DYNAMIC_OFFSET + 2 * 16 + 8, &0_u64.to_le_bytes());
+        assert_eq!(parse(&image), None, "DT_STRTAB outside every PT_LOAD");
+
+        let mut image = elf64(false, 0);
+        put(&mut image, DYNAMIC_OFFSET + 3 * 16 + 8, &u64::MAX.to_le_bytes());
+        assert_eq!(parse(&image), None, "DT_STRSZ past the end of the file");
+    }
+}
@@ -1,3 +1,4 @@
+use globset::Error as GlobError;
 use ignore::Error as WalkError;
 use regex::Error as RegexError;
 use std::io::Error as IoError;
@@ -8,8 +9,12 @@ pub enum ScanError {
     Io(#[from] IoError),
     #[error("Regex error, consider using -r to escape the Regex: {0:?}")]
     Regex(#[from] RegexError),
+    #[error("Glob error: {0}")]
+    Glob(#[from] GlobError),
     #[error("Directory traversal error: {0}")]
     Walk(#[from] WalkError),
+    #[error("Invalid --where expression at column {column}: {message}")]
+    Expression { column: usize, message: String },
     #[error("Scanit error: {0}")]
     Other(String),
 }
@@ -0,0 +1,665 @@
+use crate::{extension_bytes, read_users, AsBytes, FileNameBytes, ScanError};
+use globset::GlobBuilder;
+use ignore::DirEntry;
+use regex::bytes::Regex;
+use std::cell::OnceCell;
+use std::fs::Metadata;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// A parsed `--where` expression, tested against every entry in the worker threads.
+///
+/// Tests are combined with `and`, `or`, `not` (or `&&`, `||`, `!`) and parentheses, and two
+/// tests next to each other mean `and`:
+///
+/// | Test | Meaning |
+/// |------|---------|
+/// | `name ~ "re"`, `path ~ "re"` | filename or full path matches a regex (`!~` negates) |
+/// | `name = "*.rs"`, `path = "*/src/*"` | filename or full path matches a glob (`!=` negates) |
+/// | `ext = rs`, `ext in (rs, toml)` | extension, without the dot |
+/// | `type = f` | `f`/`file`, `d`/`dir`, `l`/`symlink`, `p`/`pipe`, `s`/`socket`, `b`/`block`, `c`/`char` |
+/// | `size > 10k` | size in bytes, with `k`, `m`, `g` or `t` (powers of 1024) |
+/// | `mtime < 2d`, `atime > 1w` | age since last modified or accessed, `s`, `min`, `h`, `d` or `w` |
+/// | `mtime > 2024-01-31` | modified after a UTC date |
+/// | `owner = root`, `owner = 1000` | owning user by name or uid (unix only) |
+/// | `depth <= 2` | depth below the search root |
+///
+/// Numbers and times compare with `<`, `<=`, `>`, `>=`, `=` and `!=`, and `in (a, b)` works
+/// wherever `=` does. Values containing spaces or operators are quoted with `"` or `'`.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{Expr, ScanError, Test};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let expr: Expr = r#"ext in (rs, toml) and size > 10k and not path ~ "/target/""#.parse()?;
+///     println!("{expr:?}");
+///
+///     assert!("size >".parse::<Expr>().is_err());
+///
+///     // A backslash only escapes the quote or another backslash, so regex escapes survive.
+///     let Expr::Test(Test::Name(re)) = r#"name ~ "\.rs$""#.parse()? else { unreachable!() };
+///     assert!(re.is_match(b"main.rs"));
+///     assert!(!re.is_match(b"foors"));
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+    Test(Test),
+}
+
+/// A single test of an [`Expr`].
+#[derive(Debug, Clone)]
+pub enum Test {
+    Name(Regex),
+    Path(Regex),
+    Extension(Box<[u8]>),
+    Type(FileKind),
+    Size(Compare, u64),
+    Modified(TimeTest),
+    Accessed(TimeTest),
+    Owner(u32),
+    Depth(Compare, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Pipe,
+    Socket,
+    Block,
+    Char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compare {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+}
+
+impl Compare {
+    fn holds<T: Ord>(self, left: &T, right: &T) -> bool {
+        match self {
+            Self::Less => left < right,
+            Self::LessEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterEqual => left >= right,
+            Self::Equal => left == right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimeTest {
+    /// How long ago, `mtime < 2d` is anything changed in the last two days.
+    Age(Compare, Duration),
+    /// A point in time, `mtime > 2024-01-31` is anything changed after that date.
+    At(Compare, SystemTime),
+}
+
+impl TimeTest {
+    fn holds(self, time: SystemTime) -> bool {
+        match self {
+            Self::Age(compare, age) => {
+                let elapsed = SystemTime::now().duration_since(time).unwrap_or_default();
+                compare.holds(&elapsed, &age)
+            }
+            Self::At(compare, at) => compare.holds(&time, &at),
+        }
+    }
+}
+
+/// The entry being tested, with its metadata fetched at most once and only if a test needs it.
+struct Subject<'a> {
+    entry: &'a DirEntry,
+    metadata: OnceCell<Option<Metadata>>,
+}
+
+impl Subject<'_> {
+    fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| self.entry.metadata().ok())
+            .as_ref()
+    }
+}
+
+impl Expr {
+    /// Whether `entry` passes the expression.
+    #[must_use]
+    pub fn matches(&self, entry: &DirEntry) -> bool {
+        self.eval(&Subject {
+            entry,
+            metadata: OnceCell::new(),
+        })
+    }
+
+    fn eval(&self, subject: &Subject) -> bool {
+        match self {
+            Self::And(left, right) => left.eval(subject) && right.eval(subject),
+            Self::Or(left, right) => left.eval(subject) || right.eval(subject),
+            Self::Not(inner) => !inner.eval(subject),
+            Self::Test(test) => test.eval(subject),
+        }
+    }
+}
+
+impl Test {
+    fn eval(&self, subject: &Subject) -> bool {
+        let entry = subject.entry;
+        match self {
+            Self::Name(re) => re.is_match(entry.filename_bytes()),
+            Self::Path(re) => re.is_match(entry.as_true_bytes()),
+            Self::Extension(extension) => {
+                extension_bytes(entry.filename_bytes()).is_some_and(|found| found == &**extension)
+            }
+            Self::Type(kind) => entry.file_type().is_some_and(|file_type| file_kind(file_type) == Some(*kind)),
+            Self::Size(compare, size) => subject
+                .metadata()
+                .is_some_and(|metadata| compare.holds(&metadata.len(), size)),
+            Self::Modified(test) => subject
+                .metadata()
+                .and_then(|metadata| metadata.modified().ok())
+                .is_some_and(|time| test.holds(time)),
+            Self::Accessed(test) => subject
+                .metadata()
+                .and_then(|metadata| metadata.accessed().ok())
+                .is_some_and(|time| test.holds(time)),
+            Self::Owner(uid) => subject.metadata().is_some_and(|metadata| owned_by(metadata, *uid)),
+            Self::Depth(compare, depth) => compare.holds(&entry.depth(), depth),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_kind(file_type: std::fs::FileType) -> Option<FileKind> {
+    use std::os::unix::fs::FileTypeExt;
+    Some(if file_type.is_file() {
+        FileKind::File
+    } else if file_type.is_dir() {
+        FileKind::Dir
+    } else if file_type.is_symlink() {
+        FileKind::Symlink
+    } else if file_type.is_fifo() {
+        FileKind::Pipe
+    } else if file_type.is_socket() {
+        FileKind::Socket
+    } else if file_type.is_block_device() {
+        FileKind::Block
+    } else if file_type.is_char_device() {
+        FileKind::Char
+    } else {
+        return None;
+    })
+}
+
+#[cfg(not(unix))]
+fn file_kind(file_type: std::fs::FileType) -> Option<FileKind> {
+    if file_type.is_file() {
+        Some(FileKind::File)
+    } else if file_type.is_dir() {
+        Some(FileKind::Dir)
+    } else if file_type.is_symlink() {
+        Some(FileKind::Symlink)
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn owned_by(metadata: &Metadata, uid: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.uid() == uid
+}
+
+#[cfg(not(unix))]
+const fn owned_by(_metadata: &Metadata, _uid: u32) -> bool {
+    false
+}
+
+/// Looks a user name up in `/etc/passwd`.
+fn uid_of(name: &str) -> Option<u32> {
+    read_users()
+        .into_iter()
+        .find(|user| *user.name == *name.as_bytes())
+        .map(|user| user.uid)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Comma,
+    And,
+    Or,
+    Not,
+    In,
+    Operator(&'static str),
+    Word(String),
+}
+
+/// Characters that end an unquoted word.
+const DELIMITERS: &str = "()<>=!~,&|\"'";
+
+const OPERATORS: [&str; 8] = ["!=", "!~", "<=", ">=", "<", ">", "=", "~"];
+
+fn error(column: usize, message: impl Into<String>) -> ScanError {
+    ScanError::Expression {
+        column,
+        message: message.into(),
+    }
+}
+
+/// Splits the expression into tokens, each with its 1-based column.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ScanError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(offset, current)) = chars.peek() {
+        let column = text[..offset].chars().count() + 1;
+        let rest = &text[offset..];
+        if current.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let symbol = [("(", Token::Open), (")", Token::Close), (",", Token::Comma), ("&&", Token::And), ("||", Token::Or)]
+            .into_iter()
+            .find(|(symbol, _)| rest.starts_with(symbol))
+            .or_else(|| {
+                OPERATORS
+                    .into_iter()
+                    .find(|operator| rest.starts_with(operator))
+                    .map(|operator| (operator, Token::Operator(operator)))
+            })
+            .or_else(|| rest.starts_with('!').then_some(("!", Token::Not)));
+        if let Some((symbol, token)) = symbol {
+            tokens.push((token, column));
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            continue;
+        }
+
+        if current == '"' || current == '\'' {
+            chars.next();
+            let mut word = String::new();
+            loop {
+                match chars.next() {
+                    // Only the quote and the backslash itself are escapes, so regex escapes
+                    // such as `\.` and `\d` reach the regex unchanged.
+                    Some((_, '\\')) => match chars.peek() {
+                        Some(&(_, escaped)) if escaped == current || escaped == '\\' => {
+                            word.push(escaped);
+                            chars.next();
+                        }
+                        Some(_) => word.push('\\'),
+                        None => return Err(error(column, "unterminated string")),
+                    },
+                    Some((_, quote)) if quote == current => break,
+                    Some((_, other)) => word.push(other),
+                    None => return Err(error(column, "unterminated string")),
+                }
+            }
+            tokens.push((Token::Word(word), column));
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&(_, next)) = chars.peek() {
+            if next.is_whitespace() || DELIMITERS.contains(next) {
+                break;
+            }
+            word.push(next);
+            chars.next();
+        }
+        if word.is_empty() {
+            return Err(error(column, format!("unexpected {current:?}")));
+        }
+        let token = match word.as_str() {
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            "in" => Token::In,
+            _ => Token::Word(word),
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    /// Column just past the end, for errors at the end of the input.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(_, column)| *column)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<(String, usize), ScanError> {
+        let column = self.column();
+        match self.next() {
+            Some(Token::Word(word)) => Ok((word, column)),
+            Some(other) => Err(error(column, format!("expected {what}, found {}", describe(&other)))),
+            None => Err(error(column, format!("expected {what}, found the end"))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ScanError> {
+        let mut left = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ScanError> {
+        let mut left = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                // Two tests in a row mean `and`, like find(1).
+                Some(Token::Word(_) | Token::Not | Token::Open) => {}
+                _ => return Ok(left),
+            }
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScanError> {
+        let column = self.column();
+        match self.peek() {
+            Some(Token::Not) => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.next();
+                let inner = self.or()?;
+                let close = self.column();
+                match self.next() {
+                    Some(Token::Close) => Ok(inner),
+                    _ => Err(error(close, format!("missing ')' for the '(' at column {column}"))),
+                }
+            }
+            _ => self.test(),
+        }
+    }
+
+    fn test(&mut self) -> Result<Expr, ScanError> {
+        let (field, field_column) = self.expect_word("a field such as name, size or type")?;
+        let operator_column = self.column();
+        let operator = match self.next() {
+            Some(Token::Operator(operator)) => operator,
+            Some(Token::In) => return self.list(&field, field_column),
+            other => {
+                let found = other.as_ref().map_or_else(|| "the end".into(), describe);
+                return Err(error(operator_column, format!("expected an operator after {field}, found {found}")));
+            }
+        };
+        let (value, value_column) = self.expect_word("a value")?;
+
+        let (negate, compare) = match operator {
+            "<" => (false, Compare::Less),
+            "<=" => (false, Compare::LessEqual),
+            ">" => (false, Compare::Greater),
+            ">=" => (false, Compare::GreaterEqual),
+            "!=" | "!~" => (true, Compare::Equal),
+            _ => (false, Compare::Equal),
+        };
+        let regex = operator.ends_with('~');
+        let ordered = compare != Compare::Equal;
+
+        let test = match field.as_str() {
+            "name" | "path" if ordered => {
+                return Err(error(operator_column, format!("{field} only supports =, !=, ~ and !~")));
+            }
+            "name" | "path" if regex => {
+                let re = Regex::new(&value)
+                    .map_err(|regex_error| error(value_column, format!("invalid regex: {regex_error}")))?;
+                if field == "name" {
+                    Test::Name(re)
+                } else {
+                    Test::Path(re)
+                }
+            }
+            _ if regex => {
+                return Err(error(operator_column, format!("~ only works on name and path, not {field}")));
+            }
+            "size" => Test::Size(compare, parse_size(&value, value_column)?),
+            "mtime" | "atime" => {
+                let time = parse_time(&value, compare, value_column)?;
+                if field == "mtime" {
+                    Test::Modified(time)
+                } else {
+                    Test::Accessed(time)
+                }
+            }
+            "depth" => Test::Depth(
+                compare,
+                value
+                    .parse()
+                    .map_err(|_| error(value_column, format!("expected a depth, found {value:?}")))?,
+            ),
+            "ext" | "type" | "owner" if ordered => {
+                return Err(error(operator_column, format!("{field} only supports =, != and in")));
+            }
+            _ => equal_test(&field, field_column, &value, value_column)?,
+        };
+
+        let test = Expr::Test(test);
+        Ok(if negate { Expr::Not(Box::new(test)) } else { test })
+    }
+
+    /// `field in (a, b, c)`, any of the values.
+    fn list(&mut self, field: &str, field_column: usize) -> Result<Expr, ScanError> {
+        let open = self.column();
+        if self.next() != Some(Token::Open) {
+            return Err(error(open, "expected '(' after in"));
+        }
+        let mut expr: Option<Expr> = None;
+        loop {
+            let (value, value_column) = self.expect_word("a value")?;
+            let test = Expr::Test(equal_test(field, field_column, &value, value_column)?);
+            expr = Some(match expr {
+                Some(previous) => Expr::Or(Box::new(previous), Box::new(test)),
+                None => test,
+            });
+            let column = self.column();
+            match self.next() {
+                Some(Token::Comma) => {}
+                Some(Token::Close) => break,
+                _ => return Err(error(column, format!("expected ',' or ')' to close the '(' at column {open}"))),
+            }
+        }
+        expr.ok_or_else(|| error(open, "empty list"))
+    }
+}
+
+/// Tests that only support equality, shared by `=` and `in`.
+fn equal_test(field: &str, field_column: usize, value: &str, value_column: usize) -> Result<Test, ScanError> {
+    Ok(match field {
+        "ext" => Test::Extension(value.strip_prefix('.').unwrap_or(value).as_bytes().into()),
+        "type" => Test::Type(parse_kind(value, value_column)?),
+        "owner" => Test::Owner(
+            value
+                .parse()
+                .ok()
+                .or_else(|| uid_of(value))
+                .ok_or_else(|| error(value_column, format!("unknown user {value:?}")))?,
+        ),
+        "name" => Test::Name(glob(value, true, value_column)?),
+        "path" => Test::Path(glob(value, false, value_column)?),
+        _ => return Err(error(field_column, format!("unknown field {field:?}, expected name, path, ext, type, size, mtime, atime, owner or depth"))),
+    })
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "'('".into(),
+        Token::Close => "')'".into(),
+        Token::Comma => "','".into(),
+        Token::And => "and".into(),
+        Token::Or => "or".into(),
+        Token::Not => "not".into(),
+        Token::In => "in".into(),
+        Token::Operator(operator) => format!("'{operator}'"),
+        Token::Word(word) => format!("{word:?}"),
+    }
+}
+
+/// Compiles a glob, where `*` only crosses a `/` for `path` tests, like `find -path`.
+fn glob(pattern: &str, literal_separator: bool, column: usize) -> Result<Regex, ScanError> {
+    GlobBuilder::new(pattern)
+        .literal_separator(literal_separator)
+        .build()
+        .map_err(|err| error(column, format!("invalid glob {pattern:?}: {}", err.kind())))
+        .and_then(|glob| Regex::new(glob.regex()).map_err(ScanError::Regex))
+}
+
+fn parse_kind(value: &str, column: usize) -> Result<FileKind, ScanError> {
+    Ok(match value {
+        "f" | "file" => FileKind::File,
+        "d" | "dir" | "directory" => FileKind::Dir,
+        "l" | "link" | "symlink" => FileKind::Symlink,
+        "p" | "pipe" | "fifo" => FileKind::Pipe,
+        "s" | "socket" => FileKind::Socket,
+        "b" | "block" => FileKind::Block,
+        "c" | "char" => FileKind::Char,
+        _ => return Err(error(column, format!("unknown type {value:?}, expected f, d, l, p, s, b or c"))),
+    })
+}
+
+/// Splits `10k` into `10` and `k`.
+fn split_number(value: &str) -> (&str, &str) {
+    let split = value
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(value.len());
+    value.split_at(split)
+}
+
+fn parse_size(value: &str, column: usize) -> Result<u64, ScanError> {
+    parse_byte_size(value).map_err(|message| error(column, message))
+}
+
+/// Parses a size such as `512`, `10k` or `2MiB`, with units in powers of 1024.
+///
+/// # Errors
+///
+/// Returns a description of the problem when the unit or number is not understood.
+///
+/// # Examples
+/// ```rust
+/// use scanit::parse_byte_size;
+///
+/// assert_eq!(parse_byte_size("10k"), Ok(10 * 1024));
+/// assert_eq!(parse_byte_size("2MiB"), Ok(2 * 1024 * 1024));
+/// assert!(parse_byte_size("3 parsecs").is_err());
+/// ```
+pub fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let (number, unit) = split_number(value);
+    let shift = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 10,
+        "m" | "mb" | "mib" => 20,
+        "g" | "gb" | "gib" => 30,
+        "t" | "tb" | "tib" => 40,
+        _ => return Err(format!("unknown size unit {unit:?}, expected k, m, g or t")),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(1 << shift))
+        .ok_or_else(|| format!("expected a size such as 10k, found {value:?}"))
+}
+
+fn parse_time(value: &str, compare: Compare, column: usize) -> Result<TimeTest, ScanError> {
+    if let Some(date) = parse_date(value) {
+        return Ok(TimeTest::At(compare, date));
+    }
+    let (number, unit) = split_number(value);
+    let seconds = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(error(column, format!("expected an age such as 2d or a date such as 2024-01-31, found {value:?}")));
+        }
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(seconds))
+        .map(|seconds| TimeTest::Age(compare, Duration::from_secs(seconds)))
+        .ok_or_else(|| error(column, format!("expected an age such as 2d, found {value:?}")))
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` date.
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar.
+    let shifted = if month <= 2 { year - 1 } else { year };
+    let era = shifted.div_euclid(400);
+    let year_of_era = shifted - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = u64::try_from(days).ok()?.checked_mul(24 * 60 * 60)?;
+    SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+}
+
+impl FromStr for Expr {
+    type Err = ScanError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+            end: text.chars().count() + 1,
+        };
+        if parser.tokens.is_empty() {
+            return Err(error(1, "empty expression"));
+        }
+        let expr = parser.or()?;
+        parser.peek().map_or(Ok(expr), |token| {
+            Err(error(parser.column(), format!("unexpected {}", describe(token))))
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use crate::SNIFF_LEN;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// A file opened once for the content filters, with its first 8 KiB already read.
+///
+/// [`crate::Sniffer`], [`crate::ElfFilter`] and [`crate::ContentMatcher`] all start from the same
+/// head, so a file checked by several of them is still only opened and read once.
+#[derive(Debug)]
+pub struct FileHead {
+    file: File,
+    len: u64,
+    head: Vec<u8>,
+}
+
+impl FileHead {
+    /// Opens the file at `path` and reads its head.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file can't be opened or read.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::FileHead;
+    ///
+    /// let cargo = FileHead::open("Cargo.toml".as_ref())?;
+    /// assert!(cargo.head().starts_with(b"[package]"));
+    /// assert!(cargo.is_complete() && cargo.len() == cargo.head().len() as u64);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        (&file).take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+        Ok(Self { file, len, head })
+    }
+
+    /// Up to the first 8 KiB of the file.
+    #[must_use]
+    pub fn head(&self) -> &[u8] {
+        &self.head
+    }
+
+    /// The file's length when it was opened.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the head holds the whole file.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.head.len() as u64 >= self.len
+    }
+
+    /// The open file. Its position is wherever the last reader left it, so seek before reading.
+    #[must_use]
+    pub const fn file(&self) -> &File {
+        &self.file
+    }
+}
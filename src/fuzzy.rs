@@ -0,0 +1,347 @@
+use crate::{build_walker, compile_pattern, entry_matcher, match_subject, AsBytes, BoxBytes, CaseMode, ScanError, SearchConfig};
+use ignore::WalkState;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::ops::Range;
+use std::sync::Mutex;
+
+const SCORE_MATCH: i32 = 16;
+const GAP_START: i32 = -3;
+const GAP_EXTENSION: i32 = -1;
+/// Matching right after a separator or at the start of the filename.
+const BONUS_BOUNDARY: i32 = 8;
+/// Matching an uppercase letter after a lowercase one, or a digit after a non-digit.
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+/// The first query byte counts its bonus this many times.
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+/// Marks cells of the score matrix that can't be reached.
+const UNREACHABLE: i32 = i32::MIN / 2;
+
+/// A fuzzy query, scored against filenames the way fzf does.
+///
+/// The query bytes have to appear in order. Matches at word boundaries, camel case humps and
+/// runs of consecutive bytes score higher, while gaps cost a little.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{CaseMode, FuzzyQuery};
+///
+/// let query = FuzzyQuery::new("mtch", CaseMode::Smart);
+/// assert!(query.score(b"matcher.rs") > query.score(b"my_test_cache.rs"));
+/// assert_eq!(query.positions(b"matcher.rs").map(|(_, positions)| positions), Some(vec![0, 2, 3, 4]));
+/// assert_eq!(query.score(b"lib.rs"), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FuzzyQuery {
+    query: Box<[u8]>,
+    case_insensitive: bool,
+}
+
+/// Bonus for matching `current` given the byte before it, if any.
+#[allow(clippy::inline_always)]
+#[inline(always)]
+const fn bonus(previous: Option<u8>, current: u8) -> i32 {
+    match previous {
+        None | Some(b'/' | b'\\' | b'_' | b'-' | b'.' | b' ') => BONUS_BOUNDARY,
+        Some(previous) if previous.is_ascii_lowercase() && current.is_ascii_uppercase() => BONUS_CAMEL,
+        Some(previous) if !previous.is_ascii_digit() && current.is_ascii_digit() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+#[allow(clippy::inline_always)]
+#[inline(always)]
+const fn reachable(score: i32) -> bool {
+    score > UNREACHABLE / 2
+}
+
+impl FuzzyQuery {
+    /// Prepares `query`, ignoring case unless `case_mode` says otherwise.
+    #[must_use]
+    pub fn new(query: &str, case_mode: CaseMode) -> Self {
+        let case_insensitive = case_mode.is_insensitive(query);
+        let query = if case_insensitive {
+            query.to_ascii_lowercase()
+        } else {
+            query.into()
+        };
+        Self {
+            query: query.into_bytes().into(),
+            case_insensitive,
+        }
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    const fn fold(&self, byte: u8) -> u8 {
+        if self.case_insensitive {
+            byte.to_ascii_lowercase()
+        } else {
+            byte
+        }
+    }
+
+    /// Cheap check that the query is a subsequence of `candidate` before scoring it.
+    fn is_subsequence(&self, candidate: &[u8]) -> bool {
+        let mut remaining = self.query.iter().peekable();
+        for &byte in candidate {
+            if remaining.next_if(|&&wanted| wanted == self.fold(byte)).is_some() && remaining.peek().is_none() {
+                return true;
+            }
+        }
+        remaining.peek().is_none()
+    }
+
+    /// Fills the score matrix, one row of `candidate.len()` cells per query byte, where each
+    /// cell is the best score with that query byte matched at that position.
+    fn matrix(&self, candidate: &[u8]) -> Option<Vec<i32>> {
+        if !self.is_subsequence(candidate) {
+            return None;
+        }
+        let width = candidate.len();
+        let bonuses: Vec<i32> = (0..width)
+            .map(|index| bonus(index.checked_sub(1).map(|before| candidate[before]), candidate[index]))
+            .collect();
+        let mut scores = vec![UNREACHABLE; self.query.len() * width];
+
+        for (row, &wanted) in self.query.iter().enumerate() {
+            let (done, rest) = scores.split_at_mut(row * width);
+            let previous = done.get((row.max(1) - 1) * width..).unwrap_or_default();
+            let current = &mut rest[..width];
+            let mut gapped = UNREACHABLE;
+            for column in 0..width {
+                if row > 0 && column >= 2 {
+                    gapped = (gapped + GAP_EXTENSION).max(previous[column - 2] + GAP_START);
+                }
+                if self.fold(candidate[column]) != wanted {
+                    continue;
+                }
+                let score = if row == 0 {
+                    bonuses[column] * FIRST_CHAR_MULTIPLIER
+                } else {
+                    let consecutive = column
+                        .checked_sub(1)
+                        .map_or(UNREACHABLE, |before| previous[before] + bonuses[column].max(BONUS_CONSECUTIVE));
+                    consecutive.max(gapped + bonuses[column])
+                };
+                if reachable(score) {
+                    current[column] = score + SCORE_MATCH;
+                }
+            }
+        }
+        Some(scores)
+    }
+
+    /// Score of the best alignment of the query in `candidate`, `None` when it doesn't match.
+    /// An empty query matches everything with a score of 0.
+    #[must_use]
+    pub fn score(&self, candidate: &[u8]) -> Option<i32> {
+        if self.query.is_empty() {
+            return Some(0);
+        }
+        let scores = self.matrix(candidate)?;
+        let last_row = &scores[scores.len() - candidate.len()..];
+        last_row.iter().copied().filter(|&score| reachable(score)).max()
+    }
+
+    /// Like [`FuzzyQuery::score`], but also returns where each query byte matched in
+    /// `candidate`.
+    #[must_use]
+    pub fn positions(&self, candidate: &[u8]) -> Option<(i32, Vec<usize>)> {
+        if self.query.is_empty() {
+            return Some((0, Vec::new()));
+        }
+        let scores = self.matrix(candidate)?;
+        let width = candidate.len();
+        let row_of = |row: usize| &scores[row * width..(row + 1) * width];
+
+        let (mut column, best) = row_of(self.query.len() - 1)
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, score)| reachable(score))
+            .max_by_key(|&(column, score)| (score, Reverse(column)))?;
+        let mut positions = vec![column];
+        for row in (1..self.query.len()).rev() {
+            let score = row_of(row)[column] - SCORE_MATCH;
+            let previous = row_of(row - 1);
+            let bonus = bonus(column.checked_sub(1).map(|before| candidate[before]), candidate[column]);
+            column = if column >= 1 && previous[column - 1] + bonus.max(BONUS_CONSECUTIVE) == score {
+                column - 1
+            } else {
+                (0..column.saturating_sub(1)).rev().find(|&before| {
+                    let gap = i32::try_from(column - before - 2).unwrap_or(i32::MAX);
+                    previous[before] + GAP_START + gap * GAP_EXTENSION + bonus == score
+                })?
+            };
+            positions.push(column);
+        }
+        positions.reverse();
+        Some((best, positions))
+    }
+}
+
+/// A path kept by [`find_fuzzy`], with its score and the byte offsets of the matched query
+/// bytes in `path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub path: BoxBytes,
+    pub score: i32,
+    pub positions: Box<[usize]>,
+}
+
+impl FuzzyMatch {
+    /// The matched positions merged into byte ranges of `path`, e.g. for highlighting them.
+    #[must_use]
+    pub fn spans(&self) -> Box<[Range<usize>]> {
+        let mut spans: Vec<Range<usize>> = Vec::with_capacity(self.positions.len());
+        for &position in &*self.positions {
+            match spans.last_mut() {
+                Some(last) if last.end == position => last.end += 1,
+                _ => spans.push(position..position + 1),
+            }
+        }
+        spans.into()
+    }
+}
+
+/// A heap slot ordered by score, then shorter paths, then paths in byte order.
+struct Candidate {
+    score: i32,
+    path: BoxBytes,
+    /// Where the filename is in `path`.
+    filename: Range<usize>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then_with(|| other.path.len().cmp(&self.path.len()))
+            .then_with(|| other.path.cmp(&self.path))
+    }
+}
+
+/// A bounded min-heap owned by one worker thread, merged into `merged` when the worker finishes.
+struct LocalBest<'a> {
+    heap: BinaryHeap<Reverse<Candidate>>,
+    limit: usize,
+    merged: &'a Mutex<Vec<Candidate>>,
+}
+
+impl LocalBest<'_> {
+    #[inline]
+    fn offer(&mut self, candidate: Candidate) {
+        if self.heap.len() < self.limit {
+            self.heap.push(Reverse(candidate));
+        } else if self.heap.peek().is_some_and(|Reverse(worst)| candidate > *worst) {
+            self.heap.pop();
+            self.heap.push(Reverse(candidate));
+        }
+    }
+}
+
+impl Drop for LocalBest<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            merged.extend(self.heap.drain().map(|Reverse(candidate)| candidate));
+        }
+    }
+}
+
+/// Finds the `limit` files whose names best match `query`, best first.
+///
+/// Filenames are scored with [`FuzzyQuery`] in the worker threads, each keeping its own heap
+/// of at most `limit` entries. The search patterns, exclusions and filter still apply, so use
+/// `"."` as the pattern to score every file. Directories are only scored with `keep_dirs`.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_fuzzy, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(".", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     let best = find_fuzzy(&search_config, "prent", 3)?;
+///     assert!(best[0].path.ends_with(b"process_entries.rs"));
+///     assert!(best.windows(2).all(|pair| pair[0].score >= pair[1].score));
+///     Ok(())
+/// }
+/// ```
+pub fn find_fuzzy(search_config: &SearchConfig, query: &str, limit: usize) -> Result<Vec<FuzzyMatch>, ScanError> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let keep_dirs = search_config.keep_dirs;
+    let fuzzy = FuzzyQuery::new(query, search_config.case_mode);
+    let merged: Mutex<Vec<Candidate>> = Mutex::new(Vec::new());
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let mut local = LocalBest {
+            heap: BinaryHeap::with_capacity(limit + 1),
+            limit,
+            merged: &merged,
+        };
+        let re = re.as_ref();
+        let fuzzy = &fuzzy;
+        Box::new(move |entry| {
+            if let Ok(entry_path) = entry {
+                if (keep_dirs || !entry_path.file_type().is_some_and(|filetype| filetype.is_dir()))
+                    && is_match(&entry_path, re)
+                {
+                    let (filename, start) = match_subject(&entry_path, false);
+                    if let Some(score) = fuzzy.score(filename) {
+                        local.offer(Candidate {
+                            score,
+                            path: entry_path.as_true_bytes().into(),
+                            filename: start..start + filename.len(),
+                        });
+                    }
+                }
+            }
+            WalkState::Continue
+        })
+    });
+
+    let mut best = merged
+        .into_inner()
+        .map_err(|_| ScanError::Other("a worker thread panicked".into()))?;
+    best.sort_unstable_by(|left, right| right.cmp(left));
+    best.truncate(limit);
+    Ok(best
+        .into_iter()
+        .map(|candidate| {
+            let positions = fuzzy
+                .positions(&candidate.path[candidate.filename.clone()])
+                .map(|(_, positions)| positions.iter().map(|position| position + candidate.filename.start).collect())
+                .unwrap_or_default();
+            FuzzyMatch {
+                path: candidate.path,
+                score: candidate.score,
+                positions,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,90 @@
+use scanit::ScanError;
+use std::env::current_dir;
+use std::io::Write;
+
+const LINK_START: &[u8] = b"\x1b]8;;";
+const LINK_END: &[u8] = b"\x1b\\";
+
+/// Wraps printed paths in OSC 8 `file://` hyperlinks so terminals can open them directly.
+pub struct Hyperlinker {
+    /// `file://hostname`
+    prefix: Vec<u8>,
+    /// The current directory, with a trailing separator, for relative paths.
+    cwd: Vec<u8>,
+}
+
+#[cfg(unix)]
+fn hostname() -> Vec<u8> {
+    let mut name = [0_u8; 256];
+    // SAFETY: the buffer is valid for `name.len()` bytes and gethostname writes at most that many.
+    let result = unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) };
+    if result != 0 {
+        return Vec::new();
+    }
+    let end = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+    name[..end].to_vec()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Vec<u8> {
+    std::env::var("COMPUTERNAME").map_or_else(|_| Vec::new(), String::into_bytes)
+}
+
+const fn is_unreserved(byte: u8) -> bool {
+    matches!(byte, b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/')
+}
+
+impl Hyperlinker {
+    pub fn new() -> Self {
+        let mut prefix = b"file://".to_vec();
+        prefix.extend(percent_encode(&hostname()));
+
+        let mut cwd = current_dir().map_or_else(
+            |_| Vec::new(),
+            |dir| dir.into_os_string().into_encoded_bytes(),
+        );
+        if !cwd.is_empty() && !cwd.ends_with(b"/") && !cwd.ends_with(b"\\") {
+            cwd.push(b'/');
+        }
+        Self { prefix, cwd }
+    }
+
+    /// Starts a link to `path`, whatever is written until [`Self::close`] becomes clickable.
+    pub fn open(&self, buffer: &mut impl Write, path: &[u8]) -> Result<(), ScanError> {
+        buffer.write_all(LINK_START)?;
+        buffer.write_all(&self.prefix)?;
+
+        let is_absolute = path.starts_with(b"/") || (cfg!(windows) && path.get(1) == Some(&b':'));
+        if cfg!(windows) {
+            buffer.write_all(b"/")?;
+        }
+        if !is_absolute {
+            buffer.write_all(&percent_encode(&self.cwd))?;
+        }
+        buffer.write_all(&percent_encode(path))?;
+        buffer.write_all(LINK_END)?;
+        Ok(())
+    }
+
+    pub fn close(buffer: &mut impl Write) -> Result<(), ScanError> {
+        buffer.write_all(LINK_START)?;
+        buffer.write_all(LINK_END)?;
+        Ok(())
+    }
+}
+
+/// Percent-encodes raw path bytes for a URL, turning Windows separators into `/`.
+fn percent_encode(bytes: &[u8]) -> Vec<u8> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    let mut encoded = Vec::with_capacity(bytes.len());
+    for &byte in bytes {
+        if cfg!(windows) && byte == b'\\' {
+            encoded.push(b'/');
+        } else if is_unreserved(byte) || (cfg!(windows) && byte == b':') {
+            encoded.push(byte);
+        } else {
+            encoded.extend_from_slice(&[b'%', HEX[usize::from(byte >> 4)], HEX[usize::from(byte & 0xf)]]);
+        }
+    }
+    encoded
+}
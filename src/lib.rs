@@ -24,25 +24,55 @@ static GLOBAL_ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL_ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-use fnmatch_regex2::glob_to_regex;
-use ignore::{DirEntry, WalkBuilder, WalkState};
+use globset::GlobBuilder;
+use ignore::{DirEntry, WalkBuilder, WalkParallel, WalkState};
 use regex::{bytes::Regex, bytes::RegexBuilder};
 pub use std::ffi::OsString;
+use std::ops::Range;
 use std::path::PathBuf;
-use std::process::exit as process_exit;
 pub use std::sync::mpsc::{channel as unbounded, Receiver,Sender};
 pub type BoxBytes = Box<[u8]>;
 use std::collections::HashSet;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::spawn;
+use std::time::Instant;
+mod matcher;
+pub use matcher::{load_patterns, Matcher};
 mod process_entries;
-use process_entries::{process_entry_fullpath, process_entry_shortpath};
-pub use process_entries::{FileNameBytes,AsBytes};
+use process_entries::{is_match_fullpath, is_match_shortpath, match_spans, match_subject, matched_pattern};
+pub use process_entries::{extension_bytes, is_separator, FileNameBytes,AsBytes};
 mod config;
 mod constants;
-pub use config::SearchConfig;
+pub use config::{CaseMode, Normalization, SearchConfig};
+mod content;
+mod count;
+mod disk_usage;
+mod elf;
 mod error;
+mod file_head;
+pub use file_head::FileHead;
+mod expr;
+mod fuzzy;
+mod magic;
+pub use fuzzy::{find_fuzzy, FuzzyMatch, FuzzyQuery};
+pub use magic::{ContentKind, FileFormat, Sniffed, Sniffer};
+pub use expr::{parse_byte_size, Compare, Expr, FileKind, Test, TimeTest};
+mod stats;
+mod summary;
+mod top_k;
+mod users;
+pub use users::{read_users, User};
+pub use content::{find_content_matches, ContentMatch, ContentMatcher};
+pub use count::{count_by_dir, count_matches, DirCount};
+pub use disk_usage::{disk_usage, DirUsage};
+pub use summary::{summarise, SummaryBy, SummaryGroup};
+pub use stats::ScanStats;
+use stats::LocalStats;
+pub use top_k::{find_largest, find_newest, find_oldest, find_smallest, find_top_k, RankBy, RankedPath};
 use constants::{AVOID, START_PREFIX};
-pub(crate) use constants::{DEPTH_CHECK, DOT_PATTERN};
+pub(crate) use constants::{DEPTH_CHECK, DOT_PATTERN, SNIFF_LEN};
+pub use elf::{ElfArch, ElfFilter, ElfInfo, Linkage};
 pub use error::ScanError;
 
 static AVOID_PATHS: OnceLock<HashSet<PathBuf>> = OnceLock::new();
@@ -67,22 +97,33 @@ fn avoid_sys_paths(path_entry: &DirEntry) -> bool {
 
 #[allow(clippy::missing_errors_doc)]
 #[must_use = "builds regex but modifies errors to map to custom error type"]
-fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, ScanError> {
+pub(crate) fn build_regex(pattern: &str, case_insensitive: bool) -> Result<Regex, ScanError> {
     RegexBuilder::new(pattern)
-        .case_insensitive(case_sensitive)
+        .case_insensitive(case_insensitive)
         .build()
         .map_err(ScanError::Regex)
 }
 
-#[must_use]
-fn process_glob_regex(glob_pattern: &str) -> String {
-    glob_to_regex(glob_pattern).map_or_else(
-        |_| {
-            eprintln!("This can't be processed as a glob pattern");
-            process_exit(1)
-        },
-        |good_pattern| good_pattern.as_str().into(),
-    )
+/// Converts a gitignore style glob into a regex over the full path of entries below `root`.
+///
+/// A glob without a `/` matches the filename at any depth, one with a leading or inner `/`
+/// is anchored to `root`, and a trailing `/` only matches directories. The second value
+/// tells whether the glob is limited to directories.
+pub(crate) fn glob_regex(glob: &str, root: &str, case_insensitive: bool) -> Result<(String, bool), ScanError> {
+    let (glob, dir_only) = glob.strip_suffix('/').map_or((glob, false), |glob| (glob, true));
+    let root = globset::escape(root.trim_end_matches('/'))
+        .replace('{', "[{]")
+        .replace('}', "[}]");
+    let full_glob = if glob.contains('/') {
+        format!("{root}/{}", glob.trim_start_matches('/'))
+    } else {
+        format!("{root}/**/{glob}")
+    };
+    let glob = GlobBuilder::new(&full_glob)
+        .literal_separator(true)
+        .case_insensitive(case_insensitive)
+        .build()?;
+    Ok((glob.regex().into(), dir_only))
 }
 
 /// Creates an iterator over files matching the given search configuration.
@@ -93,7 +134,7 @@ fn process_glob_regex(glob_pattern: &str) -> String {
 /// * `pattern` - A regex pattern (or a glob pattern if `use_glob` is true) to match against file paths.
 /// * `root` - The root directory from which to start the search.
 /// * `hide_hidden` - Whether to skip hidden files and directories.
-/// * `case_sensitive` - Whether regex matching should be case sensitive.
+/// * `case_mode` - Smart case (the default), or always/never ignore case.
 /// * `thread_count` - Number of parallel threads to use during traversal.
 /// * `keep_dirs` - Whether to include directory paths in the output.
 /// * `keep_sys_paths` - Whether system paths should be included, overriding default filtering.
@@ -101,6 +142,9 @@ fn process_glob_regex(glob_pattern: &str) -> String {
 /// * `use_glob` - If true, the input pattern is treated as a glob pattern.
 /// * `full_path` - If true, matching is performed against the full file path instead of just the filename.
 ///
+/// The walk runs on a background thread, so paths arrive while it is still going.
+/// Dropping the receiver stops the walk.
+///
 /// # Errors
 ///
 /// Returns a `ScanError` if:
@@ -114,20 +158,21 @@ fn process_glob_regex(glob_pattern: &str) -> String {
 ///
 /// # Examples
 /// ```rust
-/// use scanit::{find_files_iter, SearchConfig, ScanError};
+/// use scanit::{find_files_iter, CaseMode, ScanError, SearchConfig};
 ///
 /// fn main() -> Result<(), ScanError> {
 ///     let search_config = SearchConfig {
 ///         pattern: r".*\.rs$".into(),
 ///         root: ".".into(),
 ///         hide_hidden: true,
-///         case_sensitive: false,
+///         case_mode: CaseMode::Smart,
 ///         thread_count: 4,
 ///         keep_dirs: false,
 ///         keep_sys_paths: false,
 ///         max_depth: Some(5),
 ///         use_glob: false,
 ///         full_path: false,
+///         ..SearchConfig::default()
 ///     };
 ///     
 ///     
@@ -141,34 +186,279 @@ fn process_glob_regex(glob_pattern: &str) -> String {
 /// ```
 #[inline]
 pub fn find_files_iter(search_config: &SearchConfig) -> Result<Receiver<BoxBytes>, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let search_config = search_config.clone();
     let (tx, rx) = unbounded::<BoxBytes>();
+    spawn(move || {
+        walk_matches(&search_config, re.as_ref(), &tx, None, |entry_path, _| {
+            entry_path.as_true_bytes().into()
+        });
+    });
+    Ok(rx)
+}
 
-    let pattern_to_use = if search_config.use_glob {
-        process_glob_regex(&search_config.pattern)
-    } else {
-        search_config.pattern.clone()
-    };
+/// A matched path along with the byte ranges of `path` that the pattern matched.
+///
+/// When only filenames are matched the ranges are already shifted to index into the full path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMatch {
+    pub path: BoxBytes,
+    pub spans: Box<[Range<usize>]>,
+    /// Index of the first pattern that matched: 0 is `pattern`, then `extra_patterns` in order.
+    /// `None` when every path matches.
+    pub pattern: Option<usize>,
+}
+
+/// Same as [`find_files_iter`], but each path comes with the byte ranges the pattern matched,
+/// e.g. for highlighting them. The `"."` pattern matches everything and yields no ranges.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_matches_iter, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new("lib", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     for found in find_matches_iter(&search_config)?.iter() {
+///         for span in found.spans.iter() {
+///             assert_eq!(&found.path[span.clone()], b"lib");
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[inline]
+pub fn find_matches_iter(search_config: &SearchConfig) -> Result<Receiver<PathMatch>, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let search_config = search_config.clone();
+    let (tx, rx) = unbounded::<PathMatch>();
+    spawn(move || {
+        let full_path = search_config.use_glob || search_config.full_path;
+        walk_matches(&search_config, re.as_ref(), &tx, None, |entry_path, re| PathMatch {
+            path: entry_path.as_true_bytes().into(),
+            spans: match_spans(entry_path, re, full_path),
+            pattern: matched_pattern(entry_path, re, full_path),
+        });
+    });
+    Ok(rx)
+}
+
+/// Same as [`find_files_iter`], but also gives the [`ScanStats`] gathered during the walk.
+///
+/// Paths arrive while the walk is still going, and the stats are sent on the second receiver
+/// once it is over. Counters are kept per worker thread and merged at the end. Computing
+/// `bytes_matched` needs a `stat` call for every match, so prefer [`find_files_iter`] when the
+/// numbers are not needed. The stats receiver is disconnected without a value if a worker
+/// thread panicked.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_files_with_stats, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(r"\.rs$", ".", true, CaseMode::Smart, 4, false, false, None, false, false);
+///     let (paths, stats) = find_files_with_stats(&search_config)?;
+///
+///     let found = paths.iter().count() as u64;
+///     let stats = stats.recv().expect("the walk finished");
+///     assert_eq!(found, stats.entries_matched());
+///     println!("{} of {} entries matched", stats.entries_matched(), stats.entries_visited());
+///     Ok(())
+/// }
+/// ```
+#[inline]
+pub fn find_files_with_stats(
+    search_config: &SearchConfig,
+) -> Result<(Receiver<BoxBytes>, Receiver<ScanStats>), ScanError> {
+    spawn_with_stats(search_config, |entry_path, _| entry_path.as_true_bytes().into())
+}
+
+/// [`find_matches_iter`] and [`find_files_with_stats`] together: matches with the byte ranges
+/// the pattern matched, and the [`ScanStats`] once the walk is over.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_matches_with_stats, CaseMode, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new("lib", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///     let (found, stats) = find_matches_with_stats(&search_config)?;
+///
+///     assert!(found.iter().all(|found| found.spans.iter().all(|span| &found.path[span.clone()] == b"lib")));
+///     assert!(stats.recv().is_ok_and(|stats| stats.files_matched > 0));
+///     Ok(())
+/// }
+/// ```
+#[inline]
+pub fn find_matches_with_stats(
+    search_config: &SearchConfig,
+) -> Result<(Receiver<PathMatch>, Receiver<ScanStats>), ScanError> {
+    let full_path = search_config.use_glob || search_config.full_path;
+    spawn_with_stats(search_config, move |entry_path, re| PathMatch {
+        path: entry_path.as_true_bytes().into(),
+        spans: match_spans(entry_path, re, full_path),
+        pattern: matched_pattern(entry_path, re, full_path),
+    })
+}
+
+/// Runs [`walk_matches`] on a background thread with stats, sending them once the walk ends.
+fn spawn_with_stats<T: Send + 'static>(
+    search_config: &SearchConfig,
+    to_item: impl Fn(&DirEntry, Option<&Matcher>) -> T + Send + Sync + 'static,
+) -> Result<(Receiver<T>, Receiver<ScanStats>), ScanError> {
+    let re = compile_pattern(search_config)?;
+    let search_config = search_config.clone();
+    let (tx, rx) = unbounded::<T>();
+    let (stats_tx, stats_rx) = unbounded::<ScanStats>();
+    spawn(move || {
+        let merged = Mutex::new(ScanStats::default());
+        let start = Instant::now();
+        walk_matches(&search_config, re.as_ref(), &tx, Some(&merged), to_item);
+        // Ends the iteration over the matches before the stats arrive.
+        drop(tx);
+        if let Ok(mut stats) = merged.into_inner() {
+            stats.elapsed = start.elapsed();
+            // Nobody is waiting for the stats if the receiver is gone.
+            let _ = stats_tx.send(stats);
+        }
+    });
+    Ok((rx, stats_rx))
+}
+
+/// Walks the tree and sends every match down `tx`, merging per-thread counters into `stats` if given.
+/// `to_item` turns each matching entry into what is sent, given the compiled pattern.
+///
+/// The walk stops as soon as the receiving end is dropped, e.g. when the output pipe closes.
+fn walk_matches<T: Send>(
+    search_config: &SearchConfig,
+    re: Option<&Matcher>,
+    tx: &Sender<T>,
+    stats: Option<&Mutex<ScanStats>>,
+    to_item: impl Fn(&DirEntry, Option<&Matcher>) -> T + Sync,
+) {
+    //implementing this switch here improves performance.
+    let is_match = entry_matcher(search_config);
+
+    let counts = Arc::new(FilterCounts::default());
+    build_walker(search_config, re, stats.map(|_| Arc::clone(&counts))).run(|| {
+        let mut local = stats.map(LocalStats::new);
+        let to_item = &to_item;
+        Box::new(move |entry| {
+            let entry_path = match entry {
+                Ok(entry_path) => entry_path,
+                Err(error) => {
+                    if let Some(local) = local.as_mut() {
+                        local.stats.record_error(&error);
+                    }
+                    return WalkState::Continue;
+                }
+            };
+
+            let is_dir = entry_path
+                .file_type()
+                .is_some_and(|filetype| filetype.is_dir());
+
+            if let Some(local) = local.as_mut() {
+                if is_dir {
+                    local.stats.dirs_visited += 1;
+                } else {
+                    local.stats.files_visited += 1;
+                }
+            }
 
-    let re: Option<Regex> = if search_config.pattern == DOT_PATTERN {
-        None
+            if (!search_config.keep_dirs && is_dir) || !is_match(&entry_path, re) {
+                return WalkState::Continue;
+            }
+
+            if let Some(local) = local.as_mut() {
+                if is_dir {
+                    local.stats.dirs_matched += 1;
+                } else {
+                    local.stats.files_matched += 1;
+                    local.stats.bytes_matched +=
+                        entry_path.metadata().map_or(0, |metadata| metadata.len());
+                }
+            }
+
+            tx.send(to_item(&entry_path, re))
+                .map_or(WalkState::Quit, |()| WalkState::Continue)
+        })
+    });
+
+    if let Some(stats) = stats {
+        if let Ok(mut stats) = stats.lock() {
+            stats.skipped_paths += counts.skipped.load(AtomicOrdering::Relaxed);
+            stats.files_visited += counts.outside.load(AtomicOrdering::Relaxed);
+        }
+    }
+}
+
+/// Compiles the search patterns, returning `None` when every path matches.
+pub(crate) fn compile_pattern(search_config: &SearchConfig) -> Result<Option<Matcher>, ScanError> {
+    let matcher = Matcher::new(search_config)?;
+    Ok((!matcher.matches_everything()).then_some(matcher))
+}
+
+/// Selects the matching function for the configured mode, full path or filename only.
+pub(crate) fn entry_matcher(search_config: &SearchConfig) -> fn(&DirEntry, Option<&Matcher>) -> bool {
+    if search_config.use_glob || search_config.full_path {
+        is_match_fullpath
     } else {
-        Some(build_regex(&pattern_to_use, search_config.case_sensitive)?)
-    };
+        is_match_shortpath
+    }
+}
 
+/// Entries left out by the walker's `filter_entry`, which the workers never see.
+#[derive(Default)]
+pub(crate) struct FilterCounts {
+    /// Paths left out by the system path filter.
+    skipped: AtomicU64,
+    /// Files outside every matching directory in [`SearchConfig::match_dir`] mode.
+    outside: AtomicU64,
+}
+
+/// Builds the parallel walker shared by every search entry point.
+///
+/// When `counts` is given it counts the entries left out by the filter. In
+/// [`SearchConfig::match_dir`] mode the filter also marks the subtrees of `re`, before their
+/// children are read.
+pub(crate) fn build_walker(
+    search_config: &SearchConfig,
+    re: Option<&Matcher>,
+    counts: Option<Arc<FilterCounts>>,
+) -> WalkParallel {
+    let subtrees = re.and_then(Matcher::subtrees);
     //This just avoids unnecessary boolean checks(trivial but good to do)
     let conditional_check: bool =
         search_config.root != START_PREFIX || search_config.keep_sys_paths;
 
-    //implementing this switch here improves performance.
-    let process_entry = if search_config.use_glob || search_config.full_path {
-        process_entry_fullpath
-    } else {
-        process_entry_shortpath
-    };
-
     WalkBuilder::new(&search_config.root)
         .hidden(!search_config.hide_hidden)
-        .filter_entry(move |entry| conditional_check || avoid_sys_paths(entry))
+        .filter_entry(move |entry| {
+            let counter = if !(conditional_check || avoid_sys_paths(entry)) {
+                counts.as_ref().map(|counts| &counts.skipped)
+            } else if subtrees.as_ref().is_some_and(|subtrees| !subtrees.filter(entry)) {
+                counts.as_ref().map(|counts| &counts.outside)
+            } else {
+                return true;
+            };
+            if let Some(counter) = counter {
+                counter.fetch_add(1, AtomicOrdering::Relaxed);
+            }
+            false
+        })
         .git_global(false)
         .git_ignore(false)
         .git_exclude(false)
@@ -176,27 +466,11 @@ pub fn find_files_iter(search_config: &SearchConfig) -> Result<Receiver<BoxBytes
         .max_depth(search_config.max_depth)
         .threads(search_config.thread_count)
         .build_parallel()
-        .run(|| {
-            Box::new(|entry| {
-                entry.map_or(WalkState::Continue, |entry_path| {
-                    if !search_config.keep_dirs
-                        && entry_path
-                            .file_type()
-                            .is_some_and(|filetype| filetype.is_dir())
-                    {
-                        return WalkState::Continue;
-                    }
-
-                    process_entry(&entry_path, re.as_ref(), &tx)
-                })
-            })
-        });
-    Ok(rx)
 }
 
 /// # Examples
 /// ```
-/// use scanit::{find_files, ScanError};
+/// use scanit::{find_files, CaseMode, ScanError};
 ///
 /// fn main() -> Result<(), ScanError> {
 ///     // Find all Rust source files in current directory
@@ -204,7 +478,7 @@ pub fn find_files_iter(search_config: &SearchConfig) -> Result<Receiver<BoxBytes
 ///         r"\.rs$",        // Match files ending in .rs
 ///         ".",             // Search in current directory
 ///         true,            // Skip hidden files
-///         false,           // Case-insensitive matching
+///         CaseMode::Smart, // Ignore case unless the pattern has capitals
 ///         4,               // Use 4 parallel threads
 ///         false,           // Don't include directory paths
 ///         false,           // Skip system paths
@@ -232,7 +506,7 @@ pub fn find_files(
     pattern: &str,
     root: &str,
     hide_hidden: bool,
-    case_sensitive: bool,
+    case_mode: CaseMode,
     thread_count: usize,
     keep_dirs: bool,
     keep_sys_paths: bool,
@@ -240,18 +514,18 @@ pub fn find_files(
     use_glob: bool,
     full_path: bool,
 ) -> Result<Vec<OsString>, ScanError> {
-    let search_config = SearchConfig {
-        pattern: pattern.to_string(),
-        root: root.into(),
+    let search_config = SearchConfig::new(
+        pattern,
+        root,
         hide_hidden,
-        case_sensitive,
+        case_mode,
         thread_count,
         keep_dirs,
         keep_sys_paths,
         max_depth,
         use_glob,
         full_path,
-    };
+    );
 
     Ok(find_files_iter(&search_config)?
         .iter()
@@ -0,0 +1,236 @@
+use scanit::{extension_bytes, BoxBytes};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::{symlink_metadata, Metadata};
+use std::path::Path;
+
+/// File type keys understood in `LS_COLORS`, in the order their codes are stored.
+const TYPE_KEYS: [&[u8]; 15] = [
+    b"fi", b"di", b"ln", b"or", b"mi", b"ex", b"pi", b"so", b"bd", b"cd", b"su", b"sg", b"tw",
+    b"ow", b"st",
+];
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    File,
+    Dir,
+    Link,
+    Orphan,
+    Missing,
+    Exec,
+    Pipe,
+    Socket,
+    Block,
+    Char,
+    Setuid,
+    Setgid,
+    StickyOtherWritable,
+    OtherWritable,
+    Sticky,
+}
+
+impl Kind {
+    const fn fallback(self) -> Option<Self> {
+        match self {
+            Self::StickyOtherWritable | Self::OtherWritable | Self::Sticky => Some(Self::Dir),
+            Self::Exec | Self::Setuid | Self::Setgid => Some(Self::File),
+            Self::Missing => Some(Self::Orphan),
+            Self::Orphan => Some(Self::Link),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed `LS_COLORS` value, with every code already turned into an escape sequence.
+pub struct LsColors {
+    types: [Option<BoxBytes>; TYPE_KEYS.len()],
+    /// `*.ext` keys, looked up by the extension after the final dot.
+    extensions: HashMap<BoxBytes, BoxBytes>,
+    /// Any other `*suffix` keys, checked in order.
+    suffixes: Vec<(BoxBytes, BoxBytes)>,
+    reset: BoxBytes,
+    /// Whether any non-regular type key is set, which costs an `lstat` per path.
+    needs_metadata: bool,
+}
+
+fn escape(code: &[u8]) -> BoxBytes {
+    [b"\x1b[", code, b"m"].concat().into()
+}
+
+impl LsColors {
+    /// Reads `LS_COLORS`, returning `None` when it is unset or empty.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var_os("LS_COLORS")?;
+        if value.is_empty() {
+            return None;
+        }
+        Some(Self::parse(value.as_encoded_bytes()))
+    }
+
+    pub fn parse(value: &[u8]) -> Self {
+        let mut colours = Self {
+            types: Default::default(),
+            extensions: HashMap::new(),
+            suffixes: Vec::new(),
+            reset: escape(b"0"),
+            needs_metadata: false,
+        };
+
+        for (key, code) in value.split(|&byte| byte == b':').filter_map(|entry| {
+            let split = entry.iter().position(|&byte| byte == b'=')?;
+            Some((&entry[..split], &entry[split + 1..]))
+        }) {
+            if let Some(suffix) = key.strip_prefix(b"*") {
+                match suffix.strip_prefix(b".") {
+                    Some(extension) if !extension.contains(&b'.') => {
+                        colours.extensions.insert(extension.into(), escape(code));
+                    }
+                    _ => colours.suffixes.push((suffix.into(), escape(code))),
+                }
+            } else if key == b"rs" {
+                colours.reset = escape(code);
+            } else if let Some(index) = TYPE_KEYS.iter().position(|type_key| *type_key == key) {
+                colours.types[index] = Some(escape(code));
+            }
+        }
+
+        colours.needs_metadata = colours
+            .types
+            .iter()
+            .enumerate()
+            .any(|(index, code)| index != Kind::File as usize && code.is_some());
+        colours
+    }
+
+    pub fn reset(&self) -> &[u8] {
+        &self.reset
+    }
+
+    /// Picks the escape sequence for `path`, empty if nothing applies.
+    ///
+    /// Like `ls`, unset type keys fall back to a more general one (`tw` to `di`, `ex` to the
+    /// file's extension and so on), and extensions only apply to plain files.
+    pub fn colour(&self, path: &[u8]) -> &[u8] {
+        let mut kind = if self.needs_metadata {
+            classify(path)
+        } else {
+            Kind::File
+        };
+
+        loop {
+            if matches!(kind, Kind::File) {
+                return self
+                    .suffix_colour(path)
+                    .or(self.types[Kind::File as usize].as_deref())
+                    .unwrap_or_default();
+            }
+            if let Some(code) = &self.types[kind as usize] {
+                return code;
+            }
+            match kind.fallback() {
+                Some(general) => kind = general,
+                None => return &[],
+            }
+        }
+    }
+
+    fn suffix_colour(&self, path: &[u8]) -> Option<&[u8]> {
+        if let Some(extension) = extension_bytes(path) {
+            if let Some(code) = self.extensions.get(extension).or_else(|| {
+                self.extensions
+                    .get(extension.to_ascii_lowercase().as_slice())
+            }) {
+                return Some(code);
+            }
+        }
+        self.suffixes
+            .iter()
+            .find(|(suffix, _)| path.ends_with(suffix))
+            .map(|(_, code)| &**code)
+    }
+}
+
+fn path_from_bytes(path: &[u8]) -> &Path {
+    // SAFETY: the printed bytes come straight from `OsStr::as_encoded_bytes` (or the unix
+    // equivalent) in the walker, so they are valid encoded `OsStr` data.
+    Path::new(unsafe { OsStr::from_encoded_bytes_unchecked(path) })
+}
+
+/// Works out the `LS_COLORS` file type of `path` with an `lstat`.
+pub fn classify(path: &[u8]) -> Kind {
+    let path = path_from_bytes(path);
+    let Ok(metadata) = symlink_metadata(path) else {
+        return Kind::Missing;
+    };
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        return if path.exists() { Kind::Link } else { Kind::Orphan };
+    }
+    if file_type.is_dir() {
+        return classify_dir(&metadata);
+    }
+    if file_type.is_file() {
+        return classify_file(&metadata);
+    }
+    classify_special(&metadata)
+}
+
+#[cfg(unix)]
+fn classify_dir(metadata: &Metadata) -> Kind {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    match (mode & 0o1000 != 0, mode & 0o002 != 0) {
+        (true, true) => Kind::StickyOtherWritable,
+        (false, true) => Kind::OtherWritable,
+        (true, false) => Kind::Sticky,
+        (false, false) => Kind::Dir,
+    }
+}
+
+#[cfg(unix)]
+fn classify_file(metadata: &Metadata) -> Kind {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    if mode & 0o4000 != 0 {
+        Kind::Setuid
+    } else if mode & 0o2000 != 0 {
+        Kind::Setgid
+    } else if mode & 0o111 != 0 {
+        Kind::Exec
+    } else {
+        Kind::File
+    }
+}
+
+#[cfg(unix)]
+fn classify_special(metadata: &Metadata) -> Kind {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    if file_type.is_fifo() {
+        Kind::Pipe
+    } else if file_type.is_socket() {
+        Kind::Socket
+    } else if file_type.is_block_device() {
+        Kind::Block
+    } else if file_type.is_char_device() {
+        Kind::Char
+    } else {
+        Kind::File
+    }
+}
+
+#[cfg(not(unix))]
+const fn classify_dir(_metadata: &Metadata) -> Kind {
+    Kind::Dir
+}
+
+#[cfg(not(unix))]
+const fn classify_file(_metadata: &Metadata) -> Kind {
+    Kind::File
+}
+
+#[cfg(not(unix))]
+const fn classify_special(_metadata: &Metadata) -> Kind {
+    Kind::File
+}
@@ -0,0 +1,273 @@
+use crate::FileHead;
+use ignore::DirEntry;
+use memchr::memchr;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// A file format recognised by its magic number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Elf,
+    Pe,
+    MachO,
+    Png,
+    Jpeg,
+    Gif,
+    Pdf,
+    Zip,
+    Gzip,
+    Zstd,
+    Xz,
+    Tar,
+    Sqlite,
+    Wasm,
+    /// A script starting with `#!`.
+    Script,
+}
+
+/// Magic numbers found at the very start of a file.
+const PREFIXES: &[(&[u8], FileFormat)] = &[
+    (b"\x7fELF", FileFormat::Elf),
+    (b"\xfe\xed\xfa\xce", FileFormat::MachO),
+    (b"\xfe\xed\xfa\xcf", FileFormat::MachO),
+    (b"\xce\xfa\xed\xfe", FileFormat::MachO),
+    (b"\xcf\xfa\xed\xfe", FileFormat::MachO),
+    (b"\x89PNG\r\n\x1a\n", FileFormat::Png),
+    (b"\xff\xd8\xff", FileFormat::Jpeg),
+    (b"GIF87a", FileFormat::Gif),
+    (b"GIF89a", FileFormat::Gif),
+    (b"%PDF-", FileFormat::Pdf),
+    (b"PK\x03\x04", FileFormat::Zip),
+    (b"PK\x05\x06", FileFormat::Zip),
+    (b"PK\x07\x08", FileFormat::Zip),
+    (b"\x1f\x8b", FileFormat::Gzip),
+    (b"\x28\xb5\x2f\xfd", FileFormat::Zstd),
+    (b"\xfd7zXZ\x00", FileFormat::Xz),
+    (b"SQLite format 3\x00", FileFormat::Sqlite),
+    (b"\x00asm", FileFormat::Wasm),
+    (b"#!", FileFormat::Script),
+];
+
+/// Where a tar header keeps its `ustar` magic.
+const TAR_MAGIC_OFFSET: usize = 257;
+
+impl FileFormat {
+    /// Finds the format of a file from its first bytes, ideally a few KiB of them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::FileFormat;
+    ///
+    /// assert_eq!(FileFormat::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(FileFormat::Png));
+    /// assert_eq!(FileFormat::sniff(b"#!/bin/sh\necho hi\n"), Some(FileFormat::Script));
+    /// assert_eq!(FileFormat::sniff(b"plain text"), None);
+    /// assert_eq!(FileFormat::sniff("MZ this is a readme, not a DOS header. ".repeat(4).as_bytes()), None);
+    /// assert_eq!(FileFormat::Png.mime(), "image/png");
+    /// ```
+    #[must_use]
+    pub fn sniff(head: &[u8]) -> Option<Self> {
+        if let Some(&(_, format)) = PREFIXES.iter().find(|(magic, _)| head.starts_with(magic)) {
+            return Some(format);
+        }
+        if is_pe(head) {
+            return Some(Self::Pe);
+        }
+        // Fat Mach-O shares 0xCAFEBABE with Java classes, which have a version of 45 or more
+        // where a fat binary has its (small) architecture count.
+        if head.starts_with(b"\xca\xfe\xba\xbe")
+            && head
+                .get(4..8)
+                .is_some_and(|count| u32::from_be_bytes([count[0], count[1], count[2], count[3]]) < 45)
+        {
+            return Some(Self::MachO);
+        }
+        if head
+            .get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5)
+            .is_some_and(|magic| magic == b"ustar")
+        {
+            return Some(Self::Tar);
+        }
+        None
+    }
+
+    /// The MIME type usually given to the format.
+    #[must_use]
+    pub const fn mime(self) -> &'static str {
+        match self {
+            Self::Elf => "application/x-elf",
+            Self::Pe => "application/vnd.microsoft.portable-executable",
+            Self::MachO => "application/x-mach-binary",
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Pdf => "application/pdf",
+            Self::Zip => "application/zip",
+            Self::Gzip => "application/gzip",
+            Self::Zstd => "application/zstd",
+            Self::Xz => "application/x-xz",
+            Self::Tar => "application/x-tar",
+            Self::Sqlite => "application/vnd.sqlite3",
+            Self::Wasm => "application/wasm",
+            Self::Script => "text/x-script",
+        }
+    }
+
+    /// Whether files of this format hold text.
+    #[must_use]
+    pub const fn is_text(self) -> bool {
+        matches!(self, Self::Script)
+    }
+}
+
+/// Where an `MZ` DOS header says the PE header starts (`e_lfanew`).
+fn pe_offset(head: &[u8]) -> Option<usize> {
+    if !head.starts_with(b"MZ") {
+        return None;
+    }
+    let offset = head.get(0x3c..0x40)?;
+    usize::try_from(u32::from_le_bytes([offset[0], offset[1], offset[2], offset[3]])).ok()
+}
+
+const PE_SIGNATURE: &[u8] = b"PE\0\0";
+
+/// An `MZ` DOS header whose `e_lfanew` points at a `PE\0\0` signature within `head`.
+fn is_pe(head: &[u8]) -> bool {
+    pe_offset(head)
+        .and_then(|offset| head.get(offset..offset.checked_add(PE_SIGNATURE.len())?))
+        .is_some_and(|signature| signature == PE_SIGNATURE)
+}
+
+/// What a file looks like from its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sniffed {
+    pub format: Option<FileFormat>,
+    pub binary: bool,
+}
+
+impl Sniffed {
+    /// Sniffs `head`. A file is binary when it has a NUL byte there or a binary format.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use scanit::Sniffed;
+    ///
+    /// assert!(Sniffed::new(b"\x7fELF\x02\x01\x01").binary);
+    /// assert!(Sniffed::new(b"data\0with a nul").binary);
+    /// assert!(!Sniffed::new(b"#!/usr/bin/env python3\n").binary);
+    /// assert_eq!(Sniffed::new(b"hello\n").mime(), "text/plain");
+    /// ```
+    #[must_use]
+    pub fn new(head: &[u8]) -> Self {
+        let format = FileFormat::sniff(head);
+        Self {
+            format,
+            binary: format.map_or_else(|| memchr(0, head).is_some(), |format| !format.is_text()),
+        }
+    }
+
+    /// Reads and sniffs the start of the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the I/O error if the file can't be opened or read.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        Ok(Self::from_head(&FileHead::open(path)?))
+    }
+
+    /// Sniffs a file that is already open. Unlike [`Sniffed::new`] this also finds PE files
+    /// whose header starts past the head, by reading their signature where `e_lfanew` says.
+    #[must_use]
+    pub fn from_head(file: &FileHead) -> Self {
+        let sniffed = Self::new(file.head());
+        if sniffed.format.is_some() {
+            return sniffed;
+        }
+        let far_pe = pe_offset(file.head())
+            .filter(|&offset| offset >= file.head().len())
+            .is_some_and(|offset| {
+                let mut signature = [0; PE_SIGNATURE.len()];
+                let mut reader = file.file();
+                reader.seek(SeekFrom::Start(offset as u64)).is_ok()
+                    && reader.read_exact(&mut signature).is_ok()
+                    && signature == PE_SIGNATURE
+            });
+        if far_pe {
+            Self {
+                format: Some(FileFormat::Pe),
+                binary: true,
+            }
+        } else {
+            sniffed
+        }
+    }
+
+    /// The MIME type of the format, or `text/plain` and `application/octet-stream` for
+    /// unrecognised text and binary files.
+    #[must_use]
+    pub const fn mime(self) -> &'static str {
+        match self.format {
+            Some(format) => format.mime(),
+            None if self.binary => "application/octet-stream",
+            None => "text/plain",
+        }
+    }
+}
+
+/// Binary or text, for the `--binary` and `--text` filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Binary,
+    Text,
+}
+
+/// The `--binary`, `--text` and `--mime` filters, checked against the start of each file.
+#[derive(Debug, Clone)]
+pub struct Sniffer {
+    kind: Option<ContentKind>,
+    /// Lowercased MIME types, where `image/*` matches a whole top level type.
+    mime_types: Box<[String]>,
+}
+
+impl Sniffer {
+    /// Builds the filter, `None` when there is nothing to check.
+    #[must_use]
+    pub fn new(kind: Option<ContentKind>, mime_types: &[String]) -> Option<Self> {
+        (kind.is_some() || !mime_types.is_empty()).then(|| Self {
+            kind,
+            mime_types: mime_types.iter().map(|mime| mime.to_ascii_lowercase()).collect(),
+        })
+    }
+
+    fn mime_matches(&self, mime: &str) -> bool {
+        self.mime_types.is_empty()
+            || self.mime_types.iter().any(|wanted| {
+                wanted == "*/*"
+                    || wanted == mime
+                    || wanted
+                        .strip_suffix("/*")
+                        .is_some_and(|top| mime.split_once('/').is_some_and(|(mime_top, _)| mime_top == top))
+            })
+    }
+
+    /// Whether `entry` is a regular file passing the filters. Unreadable files never do.
+    #[must_use]
+    pub fn is_match(&self, entry: &DirEntry) -> bool {
+        entry.file_type().is_some_and(|filetype| filetype.is_file())
+            && Sniffed::from_path(entry.path()).is_ok_and(|sniffed| self.matches(sniffed))
+    }
+
+    /// [`Sniffer::is_match`] for a regular file that is already open.
+    #[must_use]
+    pub fn is_head_match(&self, file: &FileHead) -> bool {
+        self.matches(Sniffed::from_head(file))
+    }
+
+    fn matches(&self, sniffed: Sniffed) -> bool {
+        let kind_matches = match self.kind {
+            Some(ContentKind::Binary) => sniffed.binary,
+            Some(ContentKind::Text) => !sniffed.binary,
+            None => true,
+        };
+        kind_matches && self.mime_matches(sniffed.mime())
+    }
+}
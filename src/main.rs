@@ -1,14 +1,28 @@
+mod hyperlink;
+mod ls_colors;
 mod printer;
-use clap::{value_parser, ArgAction, ColorChoice, CommandFactory, Parser, ValueHint};
+mod theme;
+use clap::{
+    value_parser, ArgAction, ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum, ValueHint,
+};
 use clap_complete::aot::{generate, Shell};
-use printer::{write_paths_coloured, write_paths_plain};
+use hyperlink::Hyperlinker;
+use printer::{
+    write_content_matches, write_dir_counts, write_disk_usage, write_fuzzy_json, write_matches_coloured, write_pattern_matches, write_paths_coloured,
+    write_paths_json, write_paths_plain, write_paths_tree, write_ranked, write_stats, write_summary, Colouriser,
+};
 use regex::escape as RegexEscape;
-use scanit::{find_files_iter, ScanError, SearchConfig};
+use scanit::{
+    count_by_dir, count_matches, disk_usage, load_patterns, find_content_matches, find_files_iter, find_fuzzy, find_files_with_stats, find_matches_iter, find_matches_with_stats, find_top_k, summarise,
+    parse_byte_size, BoxBytes, CaseMode, ContentKind, DirUsage, ElfArch, ElfFilter, Linkage, Normalization, PathMatch, RankBy, ScanError, SearchConfig, SummaryBy,
+};
+use std::cmp::Reverse;
 use std::env::current_dir;
-use std::env::var;
-use std::io::stdout;
+use std::env::{args_os, var, var_os};
+use std::io::{stdout, ErrorKind, IsTerminal, Write};
 use std::path::Path;
-use std::process::exit as process_exit;
+use std::process::{exit as process_exit, ExitCode};
 mod constants;
 use constants::{AVOID, DOT_PATTERN, START_PREFIX};
 
@@ -34,7 +48,7 @@ fn resolve_directory(args_cd: bool, args_directory: Option<String>) -> String {
         let path_check = Path::new(&dir_to_use);
         if !path_check.is_dir() {
             eprintln!("{dir_to_use} is not a directory");
-            process_exit(1)
+            process_exit(i32::from(EXIT_ERROR))
         }
         dir_to_use
     }
@@ -42,13 +56,16 @@ fn resolve_directory(args_cd: bool, args_directory: Option<String>) -> String {
 
 #[derive(Parser)]
 #[command(version = env!("CARGO_PKG_VERSION"))]
-#[command(next_line_help = true,term_width = 200,color=ColorChoice::Always)]
+#[command(next_line_help = true,term_width = 200)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(
         value_name = "PATTERN",
         help = "Pattern to search for",
-        required_unless_present = "generate",
+        required_unless_present_any = ["generate", "patterns", "patterns_from"],
         index = 1
     )]
     pattern: Option<String>,
@@ -76,11 +93,19 @@ pub struct Args {
     hidden: bool,
     #[arg(
         short = 'e',
-        long = "case-insensitive",
+        long = "ignore-case",
+        visible_alias = "case-insensitive",
         default_value_t = false,
-        help = "Enable case-insensitive matching\n"
+        conflicts_with = "case_sensitive",
+        help = "Always match case-insensitively (by default case is ignored unless the pattern has an uppercase letter)\n"
     )]
-    case: bool,
+    ignore_case: bool,
+    #[arg(
+        long = "case-sensitive",
+        default_value_t = false,
+        help = "Always match case-sensitively\n"
+    )]
+    case_sensitive: bool,
     #[arg(
         short = 'n',
         long = "num-threads",
@@ -137,7 +162,7 @@ pub struct Args {
         long = "glob",
         required = false,
         default_value_t = false,
-        help = "Use a glob pattern",
+        help = "Use a gitignore style glob: matches the filename unless it contains a /, a trailing / only matches directories",
         conflicts_with = "regex_escape"
     )]
     glob: bool,
@@ -150,14 +175,424 @@ pub struct Args {
         conflicts_with = "glob"
     )]
     full_path: bool,
+    #[arg(
+        long = "largest",
+        value_name = "N",
+        group = "rank",
+        help = "Show the N largest matching files with their sizes, largest first"
+    )]
+    largest: Option<usize>,
+    #[arg(
+        long = "smallest",
+        value_name = "N",
+        group = "rank",
+        help = "Show the N smallest matching files with their sizes, smallest first"
+    )]
+    smallest: Option<usize>,
+    #[arg(
+        long = "newest",
+        value_name = "N",
+        group = "rank",
+        help = "Show the N most recently modified matching files with their modification times (UTC), newest first"
+    )]
+    newest: Option<usize>,
+    #[arg(
+        long = "oldest",
+        value_name = "N",
+        group = "rank",
+        help = "Show the N least recently modified matching files with their modification times (UTC), oldest first"
+    )]
+    oldest: Option<usize>,
+    #[arg(
+        long = "stats",
+        default_value_t = false,
+        conflicts_with = "rank",
+        help = "Print walk statistics to stderr once the search finishes"
+    )]
+    stats: bool,
+    #[arg(
+        long = "summary-by",
+        value_name = "GROUP",
+        value_parser = ["ext", "dir", "owner", "depth"],
+        group = "report",
+        conflicts_with_all = ["rank", "stats"],
+        help = "Print the count and total size of matched files grouped by ext, dir, owner or depth"
+    )]
+    summary_by: Option<String>,
+    #[arg(
+        long = "count",
+        default_value_t = false,
+        conflicts_with_all = ["rank", "stats", "summary_by"],
+        help = "Print only the number of matches"
+    )]
+    count: bool,
+    #[arg(
+        long = "count-by",
+        value_name = "GROUP",
+        value_parser = ["dir"],
+        group = "report",
+        conflicts_with_all = ["rank", "stats"],
+        help = "Print the number of matches in each directory"
+    )]
+    count_by: Option<String>,
+    #[arg(
+        long = "csv",
+        default_value_t = false,
+        requires = "report",
+        help = "Write the --summary-by or --count-by report as CSV"
+    )]
+    csv: bool,
+    #[arg(
+        long = "tree",
+        default_value_t = false,
+        conflicts_with_all = ["rank", "summary_by", "stats", "count", "count_by", "fuzzy", "line_number"],
+        help = "Print matches as a tree under the search root (buffers all results)"
+    )]
+    tree: bool,
+    #[arg(
+        long = "hyperlink",
+        default_value_t = false,
+        help = "Make each path a clickable file:// link in terminals that support OSC 8 (kitty, WezTerm, iTerm2)"
+    )]
+    hyperlink: bool,
+    #[arg(
+        short = 'p',
+        long = "pattern",
+        value_name = "PATTERN",
+        action = ArgAction::Append,
+        help = "Search for this pattern as well, can be repeated (any one matching is enough unless --and)\nWith -p the first positional argument is the path"
+    )]
+    patterns: Vec<String>,
+    #[arg(
+        long = "patterns-from",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        help = "Read more patterns from FILE, one per line, skipping blank lines and # comments\nLike -p, the first positional argument is then the path"
+    )]
+    patterns_from: Option<String>,
+    #[arg(
+        long = "show-pattern",
+        default_value_t = false,
+        conflicts_with_all = ["rank", "report", "count", "stats", "tree"],
+        help = "Print the pattern that matched before each path, separated by a tab"
+    )]
+    show_pattern: bool,
+    #[arg(
+        long = "where",
+        value_name = "EXPR",
+        help = "Only keep matches passing EXPR, e.g. 'ext in (rs, toml) and size > 10k and not path ~ \"/target/\"'\nTests: name, path (~ regex, = glob), ext, type, size, mtime, atime, owner, depth; and/or/not/()\nDirectories still need -i"
+    )]
+    where_expr: Option<String>,
+    #[arg(
+        long = "and",
+        default_value_t = false,
+        help = "Require every pattern to match instead of any one"
+    )]
+    and: bool,
+    #[arg(
+        long = "not",
+        value_name = "PATTERN",
+        action = ArgAction::Append,
+        help = "Leave out paths matching this pattern, can be repeated"
+    )]
+    not: Vec<String>,
+    #[arg(
+        long = "match-dir",
+        default_value_t = false,
+        conflicts_with = "fuzzy",
+        help = "Match the patterns against directory names below PATH and show every file under a matching directory"
+    )]
+    match_dir: bool,
+    #[arg(
+        long = "parent",
+        value_name = "PATTERN",
+        help = "Only keep entries whose immediate parent directory name matches PATTERN"
+    )]
+    parent: Option<String>,
+    #[arg(
+        long = "normalize",
+        value_name = "FORM",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "nfc",
+        conflicts_with = "fuzzy",
+        help = "Compare filenames and patterns in Unicode normal form nfc (default) or nfkc, so decomposed names from macOS still match\nPaths are printed unchanged, names that aren't UTF-8 are matched as bytes"
+    )]
+    normalize: Option<NormalizeForm>,
+    #[arg(
+        long = "contains",
+        value_name = "REGEX",
+        help = "Only keep files whose contents match REGEX (binary files are skipped)"
+    )]
+    contains: Option<String>,
+    #[arg(
+        long = "max-filesize",
+        value_name = "SIZE",
+        requires = "contains",
+        value_parser = parse_byte_size,
+        help = "Don't search the contents of files larger than SIZE, e.g. 10m"
+    )]
+    max_filesize: Option<u64>,
+    #[arg(
+        long = "line-number",
+        default_value_t = false,
+        requires = "contains",
+        conflicts_with_all = ["rank", "report", "count", "stats", "tree", "show_pattern", "quiet", "fuzzy"],
+        help = "Print the line of the first content match after each path, as path:line"
+    )]
+    line_number: bool,
+    #[arg(
+        long = "binary",
+        default_value_t = false,
+        conflicts_with = "text",
+        help = "Only keep binary files: a NUL byte in the first 8 KiB or a known binary format"
+    )]
+    binary: bool,
+    #[arg(
+        long = "text",
+        default_value_t = false,
+        help = "Only keep text files"
+    )]
+    text: bool,
+    #[arg(
+        long = "mime",
+        value_name = "TYPE",
+        action = ArgAction::Append,
+        help = "Only keep files whose sniffed type is TYPE, e.g. image/* or application/x-elf, can be repeated\nKnown: ELF, PE, Mach-O, PNG, JPEG, GIF, PDF, ZIP, gzip, zstd, xz, tar, SQLite, wasm, #! scripts, text/plain"
+    )]
+    mime: Vec<String>,
+    #[arg(
+        long = "elf",
+        value_name = "PROPERTY",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        action = ArgAction::Append,
+        help = "Only keep ELF files, read from their headers, with every PROPERTY given, e.g. --elf=aarch64,static\nstatic means no program interpreter and no DT_NEEDED entries, stripped means no .symtab"
+    )]
+    elf: Option<Vec<ElfProperty>>,
+    #[arg(
+        long = "needs",
+        value_name = "LIB",
+        action = ArgAction::Append,
+        help = "Only keep ELF files with a DT_NEEDED entry for LIB, e.g. libssl.so.1.1, can be repeated"
+    )]
+    needs: Vec<String>,
+    #[arg(
+        long = "fuzzy",
+        default_value_t = false,
+        conflicts_with_all = ["glob", "regex_escape", "full_path", "rank", "report", "count", "stats", "tree", "show_pattern", "patterns", "patterns_from", "and", "quiet"],
+        help = "Treat PATTERN as a fuzzy query and show the best scoring filenames, best first (-t sets how many, default 20)"
+    )]
+    fuzzy: bool,
+    #[arg(
+        long = "json",
+        default_value_t = false,
+        conflicts_with_all = ["report", "count", "stats", "tree", "show_pattern", "quiet"],
+        help = "Write one JSON object per line: {\"path\": ...}, plus \"score\" with --fuzzy, or \"size\" and \"modified\"\n(seconds since the Unix epoch) with --largest, --smallest, --newest and --oldest\nPaths that aren't UTF-8 also get \"path_bytes\", an array of their exact bytes"
+    )]
+    json: bool,
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        default_value_t = false,
+        help = "Print nothing, stop at the first match and only report it through the exit status"
+    )]
+    quiet: bool,
     #[arg(
         long = "colour",
         alias = "color",
-        required = false,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always",
+        help = "When to colour the output: auto (default, only on a terminal), always or never\nRespects NO_COLOR and CLICOLOR_FORCE, SCANIT_COLOUR=true also forces colours"
+    )]
+    colour: Option<ColourWhen>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NormalizeForm {
+    Nfc,
+    Nfkc,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ElfProperty {
+    #[value(name = "x86_64")]
+    X86_64,
+    Aarch64,
+    Static,
+    Dynamic,
+    Stripped,
+    Unstripped,
+}
+
+/// Builds the filter for `--elf` and `--needs`, `None` when neither was given.
+fn elf_filter(properties: Option<Vec<ElfProperty>>, needs: Vec<String>) -> Result<Option<ElfFilter>, ScanError> {
+    if properties.is_none() && needs.is_empty() {
+        return Ok(None);
+    }
+    let mut filter = ElfFilter {
+        needs,
+        ..ElfFilter::default()
+    };
+    for property in properties.unwrap_or_default() {
+        let conflict = match property {
+            ElfProperty::X86_64 => filter.arch.replace(ElfArch::X86_64).is_some_and(|arch| arch != ElfArch::X86_64),
+            ElfProperty::Aarch64 => filter.arch.replace(ElfArch::Aarch64).is_some_and(|arch| arch != ElfArch::Aarch64),
+            ElfProperty::Static => filter.linkage.replace(Linkage::Static).is_some_and(|linkage| linkage != Linkage::Static),
+            ElfProperty::Dynamic => filter.linkage.replace(Linkage::Dynamic).is_some_and(|linkage| linkage != Linkage::Dynamic),
+            ElfProperty::Stripped => filter.stripped.replace(true) == Some(false),
+            ElfProperty::Unstripped => filter.stripped.replace(false) == Some(true),
+        };
+        if conflict {
+            return Err(ScanError::Other("--elf properties contradict each other, nothing could match".into()));
+        }
+    }
+    Ok(Some(filter))
+}
+
+impl From<NormalizeForm> for Normalization {
+    fn from(form: NormalizeForm) -> Self {
+        match form {
+            NormalizeForm::Nfc => Self::Nfc,
+            NormalizeForm::Nfkc => Self::Nfkc,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColourWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColourWhen {
+    /// Finds `--colour[=WHEN]` before clap runs, so that help and errors can be coloured to match.
+    fn from_raw_args() -> Self {
+        for arg in args_os().skip(1) {
+            let arg = arg.to_string_lossy();
+            if arg == "--" {
+                break;
+            }
+            // Only `--colour` itself or `--colour=WHEN`, not longer flags such as `--colourful`.
+            if let Some(value) = ["--colour", "--color"]
+                .iter()
+                .find_map(|flag| arg.strip_prefix(flag))
+                .filter(|value| value.is_empty() || value.starts_with('='))
+            {
+                return match value {
+                    "" | "=always" => Self::Always,
+                    "=never" => Self::Never,
+                    _ => Self::Auto,
+                };
+            }
+        }
+        Self::Auto
+    }
+
+    /// Settles `auto` by looking at the environment and whether stdout is a terminal.
+    fn enabled(self) -> bool {
+        let env_set = |name: &str| var_os(name).is_some_and(|value| !value.is_empty());
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                if env_set("NO_COLOR") {
+                    false
+                } else if var_os("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0")
+                    || var("SCANIT_COLOUR").is_ok_and(|check| check.to_lowercase() == "true")
+                {
+                    true
+                } else {
+                    stdout().is_terminal()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the heaviest directories by disk usage, like du
+    Du(DuArgs),
+}
+
+#[derive(clap::Args)]
+pub struct DuArgs {
+    #[arg(
+        value_name = "PATH",
+        help = "Directory to measure (defaults to the current directory)",
+        value_hint = ValueHint::DirPath
+    )]
+    directory: Option<String>,
+    #[arg(
+        short = 'd',
+        long = "depth",
+        default_value_t = 1,
+        help = "Only show directories up to this depth below PATH"
+    )]
+    depth: usize,
+    #[arg(
+        short = 't',
+        long = "top",
+        help = "Only show the t heaviest directories"
+    )]
+    top_n: Option<usize>,
+    #[arg(
+        long = "apparent-size",
+        default_value_t = false,
+        help = "Sort by apparent size (sum of file lengths) instead of allocated size"
+    )]
+    apparent: bool,
+    #[arg(
+        short = 'n',
+        long = "num-threads",
+        default_value_t = env!("CPU_COUNT").parse::<usize>().unwrap_or(1),
+        help = "Number of threads to use, defaults to available threads-1",
+        value_name = "num-threads"
+    )]
+    thread_num: usize,
+    #[arg(
+        short = 's',
+        long = "sys-paths",
         default_value_t = false,
-        help = "Use custom colouring, this is WIP!"
+        help = format!("Include system paths {:?}\n", AVOID)
     )]
-    colour: bool,
+    keep_sys_paths: bool,
+}
+
+fn run_disk_usage(args: DuArgs) -> Result<bool, ScanError> {
+    let root = resolve_directory(false, Some(args.directory.unwrap_or_else(|| DOT_PATTERN.into())));
+    let search_config = SearchConfig::new(
+        DOT_PATTERN,
+        &root,
+        true,
+        CaseMode::Smart,
+        args.thread_num,
+        true,
+        args.keep_sys_paths,
+        None,
+        false,
+        false,
+    );
+
+    let mut usage: Vec<DirUsage> = disk_usage(&search_config)?
+        .into_iter()
+        .filter(|dir| dir.depth <= args.depth)
+        .collect();
+    if args.apparent {
+        usage.sort_by_key(|dir| Reverse(dir.apparent));
+    }
+    usage.truncate(args.top_n.unwrap_or(usize::MAX));
+
+    write_disk_usage(&usage)?;
+    Ok(!usage.is_empty())
 }
 
 fn escape_regex_string(input: &str, avoid_regex: bool, args_glob: bool) -> String {
@@ -167,8 +602,36 @@ fn escape_regex_string(input: &str, avoid_regex: bool, args_glob: bool) -> Strin
     RegexEscape(input)
 }
 
-fn main() -> Result<(), ScanError> {
-    let args: Args = Args::parse();
+/// Exit statuses, like grep: something matched, nothing matched, or something went wrong.
+const EXIT_MATCHED: u8 = 0;
+const EXIT_NO_MATCH: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+/// How many results `--fuzzy` shows without `-t`.
+const FUZZY_LIMIT: usize = 20;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::from(EXIT_MATCHED),
+        Ok(false) => ExitCode::from(EXIT_NO_MATCH),
+        // The reader went away (`scanit . | head`), which is not an error.
+        Err(ScanError::Io(error)) if error.kind() == ErrorKind::BrokenPipe => ExitCode::from(EXIT_MATCHED),
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+/// Runs the search, returning whether anything matched.
+fn run() -> Result<bool, ScanError> {
+    let colour_choice = if ColourWhen::from_raw_args().enabled() {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    };
+    let args = Args::from_arg_matches(&Args::command().color(colour_choice).get_matches())
+        .unwrap_or_else(|error| error.exit());
 
     if let Some(generator) = args.generate {
         let mut cmd = Args::command();
@@ -179,19 +642,51 @@ fn main() -> Result<(), ScanError> {
             cmd_clone.get_name().to_string(),
             &mut stdout(),
         );
-        return Ok(());
+        return Ok(true);
+    }
+
+    if let Some(Command::Du(du_args)) = args.command {
+        return run_disk_usage(du_args);
+    }
+
+    let mut listed = args.patterns;
+    if let Some(file) = &args.patterns_from {
+        listed.extend(load_patterns(file)?);
     }
 
-    let pattern = args.pattern.unwrap_or_else(|| {
+    // With -p every pattern comes from the flag, so the positionals shift along to the path.
+    let (mut patterns, directory) = if listed.is_empty() && args.patterns_from.is_none() {
+        (args.pattern.into_iter().collect(), args.directory)
+    } else if args.directory.is_some() {
+        eprintln!("Error: With -p the patterns go after -p and only the path is positional");
+        process_exit(i32::from(EXIT_ERROR))
+    } else {
+        (listed, args.pattern)
+    };
+    if patterns.is_empty() {
         eprintln!("Error: Please provide a search pattern");
-        process_exit(1)
-    });
+        process_exit(i32::from(EXIT_ERROR))
+    }
+    let escape = |pattern: &String| escape_regex_string(pattern, args.regex_escape, args.glob);
+    let pattern_names = patterns.clone();
+    let extra_patterns = patterns.split_off(1).iter().map(escape).collect();
 
-    let search_config = SearchConfig::new(
-        &escape_regex_string(&pattern, args.regex_escape, args.glob),
-        &resolve_directory(args.current_directory, args.directory),
+    let case_mode = if args.ignore_case {
+        CaseMode::Insensitive
+    } else if args.case_sensitive {
+        CaseMode::Sensitive
+    } else {
+        CaseMode::Smart
+    };
+
+    // A fuzzy query is scored separately, every path goes through the usual matcher.
+    let fuzzy_query = args.fuzzy.then(|| std::mem::replace(&mut patterns[0], DOT_PATTERN.into()));
+
+    let mut search_config = SearchConfig::new(
+        &escape(&patterns[0]),
+        &resolve_directory(args.current_directory, directory),
         args.hidden,
-        args.case,
+        case_mode,
         args.thread_num,
         args.keep_dirs,
         args.keep_sys_paths,
@@ -199,14 +694,144 @@ fn main() -> Result<(), ScanError> {
         args.glob,
         args.full_path,
     );
+    search_config.extra_patterns = extra_patterns;
+    search_config.match_all = args.and;
+    search_config.exclude_patterns = args.not.iter().map(escape).collect();
+    search_config.filter = args.where_expr.as_deref().map(str::parse).transpose()?;
+    search_config.match_dir = args.match_dir;
+    search_config.parent_pattern = args.parent.as_ref().map(escape);
+    search_config.normalize = args.normalize.map(Normalization::from);
+    search_config.contains = args
+        .contains
+        .as_ref()
+        .map(|pattern| escape_regex_string(pattern, args.regex_escape, false));
+    search_config.max_filesize = args.max_filesize;
+    search_config.content_kind = if args.binary {
+        Some(ContentKind::Binary)
+    } else {
+        args.text.then_some(ContentKind::Text)
+    };
+    search_config.mime_types = args.mime;
+    search_config.elf = elf_filter(args.elf, args.needs)?;
+
+    if args.quiet {
+        // Dropping the receiver after the first path makes the walker quit.
+        return Ok(find_files_iter(&search_config)?.recv().is_ok());
+    }
+
+    let colours = args
+        .colour
+        .unwrap_or(ColourWhen::Auto)
+        .enabled()
+        .then(Colouriser::from_env);
+    let links = args.hyperlink.then(Hyperlinker::new);
+    let links = links.as_ref();
+
+    if let Some(query) = fuzzy_query {
+        let found = find_fuzzy(&search_config, &query, args.top_n.unwrap_or(FUZZY_LIMIT))?;
+        if args.json {
+            write_fuzzy_json(&found)?;
+        } else if let Some(colours) = colours.as_ref() {
+            let highlighted = found.iter().map(|found| PathMatch {
+                path: found.path.clone(),
+                spans: found.spans(),
+                pattern: None,
+            });
+            write_matches_coloured(highlighted, None, colours, links)?;
+        } else {
+            write_paths(found.iter().map(|found| found.path.clone()), None, None, links)?;
+        }
+        return Ok(!found.is_empty());
+    }
+
+    if args.line_number {
+        return Ok(write_content_matches(&find_content_matches(&search_config)?, args.top_n, args.json)? > 0);
+    }
+
+    let ranking = [
+        (args.largest, RankBy::Largest),
+        (args.smallest, RankBy::Smallest),
+        (args.newest, RankBy::Newest),
+        (args.oldest, RankBy::Oldest),
+    ]
+    .into_iter()
+    .find_map(|(limit, rank)| limit.map(|limit| (rank, limit)));
+
+    if let Some((rank, limit)) = ranking {
+        let ranked = find_top_k(&search_config, rank, limit)?;
+        return Ok(write_ranked(&ranked, rank, args.top_n, args.json, colours.as_ref(), links)? > 0);
+    }
+
+    if args.json {
+        return Ok(write_paths_json(&find_files_iter(&search_config)?, args.top_n)? > 0);
+    }
+
+    if args.show_pattern {
+        let names: Vec<&str> = pattern_names.iter().map(String::as_str).collect();
+        return Ok(write_pattern_matches(&find_matches_iter(&search_config)?, args.top_n, &names)? > 0);
+    }
+
+    if args.count_by.is_some() {
+        let counts = count_by_dir(&search_config)?;
+        write_dir_counts(&counts, args.csv)?;
+        return Ok(!counts.is_empty());
+    }
+
+    if args.count {
+        let count = count_matches(&search_config)?;
+        writeln!(stdout().lock(), "{count}")?;
+        return Ok(count > 0);
+    }
+
+    if let Some(group_by) = args.summary_by {
+        let by = match group_by.as_str() {
+            "dir" => SummaryBy::Directory,
+            "owner" => SummaryBy::Owner,
+            "depth" => SummaryBy::Depth,
+            _ => SummaryBy::Extension,
+        };
+        let groups = summarise(&search_config, by)?;
+        write_summary(&groups, by, args.csv)?;
+        return Ok(!groups.is_empty());
+    }
+
+    if args.stats {
+        let stats = if let Some(colours) = colours.as_ref() {
+            let (found, stats) = find_matches_with_stats(&search_config)?;
+            write_matches_coloured(&found, args.top_n, colours, links)?;
+            stats
+        } else {
+            let (files_to_print, stats) = find_files_with_stats(&search_config)?;
+            write_paths(&files_to_print, args.top_n, None, links)?;
+            stats
+        };
+        let stats = stats
+            .recv()
+            .map_err(|_| ScanError::Other("a worker thread panicked".into()))?;
+        write_stats(&stats)?;
+        return Ok(stats.entries_matched() > 0);
+    }
+
+    if let (Some(colours), false) = (colours.as_ref(), args.tree) {
+        return Ok(write_matches_coloured(&find_matches_iter(&search_config)?, args.top_n, colours, links)? > 0);
+    }
 
     let files_to_print = find_files_iter(&search_config)?;
+    if args.tree {
+        return Ok(write_paths_tree(&files_to_print, &search_config.root, args.top_n, colours.as_ref(), links)? > 0);
+    }
+    Ok(write_paths(&files_to_print, args.top_n, colours.as_ref(), links)? > 0)
+}
 
-    if args.colour || var("SCANIT_COLOUR").is_ok_and(|check| check.to_lowercase() == "true") {
-        write_paths_coloured(&files_to_print, args.top_n)?;
+fn write_paths(
+    paths: impl IntoIterator<Item = BoxBytes>,
+    limit: Option<usize>,
+    colours: Option<&Colouriser>,
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
+    if let Some(colours) = colours {
+        write_paths_coloured(paths, limit, colours, links)
     } else {
-        write_paths_plain(&files_to_print, args.top_n)?;
+        write_paths_plain(paths, limit, links)
     }
-
-    Ok(())
 }
@@ -0,0 +1,812 @@
+use crate::{
+    build_regex, extension_bytes, ContentMatcher, ElfFilter, FileHead, glob_regex, AsBytes, BoxBytes, Expr, FileNameBytes, Normalization, ScanError, SearchConfig, Sniffer,
+    DOT_PATTERN,
+};
+use aho_corasick::AhoCorasick;
+use ignore::DirEntry;
+use memchr::memmem::Finder;
+use regex::bytes::{Regex, RegexSet, RegexSetBuilder};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::hash::BuildHasher;
+use std::iter::once;
+use std::path::Path;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// One compiled search pattern.
+///
+/// Patterns without regex metacharacters skip the regex engine: a plain substring is searched
+/// with `memmem`, and `^foo`, `foo$`, `\.rs$` and `^foo$` become prefix, suffix, extension
+/// and whole-name comparisons.
+enum Pattern {
+    Regex(Regex),
+    Contains(Box<Finder<'static>>),
+    Prefix(Literal),
+    Suffix(Literal),
+    /// Extension without the dot, compared against [`extension_bytes`].
+    Extension(Literal),
+    Exact(Literal),
+}
+
+/// A fixed string compared as a whole, optionally ignoring ASCII case.
+struct Literal {
+    bytes: Box<[u8]>,
+    ignore_case: bool,
+}
+
+impl Literal {
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn eq(&self, subject: &[u8]) -> bool {
+        if self.ignore_case {
+            self.bytes.eq_ignore_ascii_case(subject)
+        } else {
+            *self.bytes == *subject
+        }
+    }
+}
+
+/// Characters `regex::escape` puts a backslash in front of.
+const ESCAPED: &str = "\\.+*?()|[]{}^$#&-~";
+
+/// What a pattern without metacharacters has to match.
+struct Parsed {
+    text: Vec<u8>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+/// Reads `pattern` as a fixed string, returning `None` if it uses any regex feature
+/// beyond `^`/`$` anchors and escaped punctuation (what `regex::escape` produces).
+fn parse_literal(pattern: &str) -> Option<Parsed> {
+    let (anchored_start, rest) = pattern
+        .strip_prefix('^')
+        .map_or((false, pattern), |rest| (true, rest));
+
+    let mut text = Vec::with_capacity(rest.len());
+    let mut anchored_end = false;
+    let mut chars = rest.chars();
+    while let Some(current) = chars.next() {
+        match current {
+            '\\' => match chars.next() {
+                Some(escaped) if ESCAPED.contains(escaped) => text.push(escaped as u8),
+                _ => return None,
+            },
+            '$' if chars.as_str().is_empty() => anchored_end = true,
+            '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => return None,
+            other => text.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    Some(Parsed {
+        text,
+        anchored_start,
+        anchored_end,
+    })
+}
+
+/// Whether ASCII case folding gives the same answer as the regex engine's Unicode folding.
+///
+/// `k` and `s` also fold to the Kelvin sign and the long s, so they are left to the regex.
+fn ascii_fold_is_exact(text: &[u8]) -> bool {
+    text.is_ascii()
+        && !text
+            .iter()
+            .any(|byte| matches!(byte.to_ascii_lowercase(), b'k' | b's'))
+}
+
+impl Pattern {
+    /// Compiles `pattern`, picking the literal fast path whenever it gives the same results
+    /// as the regex would.
+    fn new(pattern: &str, case_insensitive: bool) -> Result<Self, ScanError> {
+        Self::literal(pattern, case_insensitive)
+            .map_or_else(|| build_regex(pattern, case_insensitive).map(Self::Regex), Ok)
+    }
+
+    fn literal(pattern: &str, case_insensitive: bool) -> Option<Self> {
+        let parsed = parse_literal(pattern)?;
+        let has_letters = parsed.text.iter().any(u8::is_ascii_alphabetic) || !parsed.text.is_ascii();
+        let ignore_case = case_insensitive && has_letters;
+        if ignore_case && !ascii_fold_is_exact(&parsed.text) {
+            return None;
+        }
+
+        let literal = |bytes: &[u8]| Literal {
+            bytes: bytes.into(),
+            ignore_case,
+        };
+        Some(match (parsed.anchored_start, parsed.anchored_end) {
+            (true, true) => Self::Exact(literal(&parsed.text)),
+            (true, false) => Self::Prefix(literal(&parsed.text)),
+            (false, true) => match parsed.text.strip_prefix(b".") {
+                // Anything that could be a separator stays a plain suffix.
+                Some(extension)
+                    if !extension.is_empty()
+                        && !extension.iter().any(|byte| matches!(byte, b'.' | b'/' | b'\\')) =>
+                {
+                    Self::Extension(literal(extension))
+                }
+                _ => Self::Suffix(literal(&parsed.text)),
+            },
+            // `memmem` has no case-insensitive search.
+            (false, false) if ignore_case => return None,
+            (false, false) => Self::Contains(Box::new(Finder::new(&parsed.text).into_owned())),
+        })
+    }
+
+    /// The fixed string this pattern looks for, where it has to sit and whether it ignores ASCII case.
+    fn literal_parts(&self) -> Option<(Vec<u8>, Anchor, bool)> {
+        match self {
+            Self::Regex(_) => None,
+            Self::Contains(finder) => Some((finder.needle().to_vec(), Anchor::Anywhere, false)),
+            Self::Prefix(literal) => Some((literal.bytes.to_vec(), Anchor::Start, literal.ignore_case)),
+            Self::Suffix(literal) => Some((literal.bytes.to_vec(), Anchor::End, literal.ignore_case)),
+            Self::Extension(literal) => Some(([b".", &*literal.bytes].concat(), Anchor::End, literal.ignore_case)),
+            Self::Exact(literal) => Some((literal.bytes.to_vec(), Anchor::Whole, literal.ignore_case)),
+        }
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn is_match(&self, subject: &[u8]) -> bool {
+        match self {
+            Self::Regex(re) => re.is_match(subject),
+            Self::Contains(finder) => finder.find(subject).is_some(),
+            Self::Prefix(literal) => subject
+                .get(..literal.bytes.len())
+                .is_some_and(|start| literal.eq(start)),
+            Self::Suffix(literal) => subject
+                .len()
+                .checked_sub(literal.bytes.len())
+                .is_some_and(|start| literal.eq(&subject[start..])),
+            Self::Extension(literal) => extension_bytes(subject).is_some_and(|extension| literal.eq(extension)),
+            Self::Exact(literal) => literal.eq(subject),
+        }
+    }
+
+    /// Byte ranges of `subject` the pattern matched, empty matches left out.
+    fn find_spans(&self, subject: &[u8]) -> Vec<Range<usize>> {
+        match self {
+            Self::Regex(re) => re
+                .find_iter(subject)
+                .filter(|found| !found.is_empty())
+                .map(|found| found.range())
+                .collect(),
+            Self::Contains(finder) => {
+                let length = finder.needle().len();
+                if length == 0 {
+                    return Vec::new();
+                }
+                finder
+                    .find_iter(subject)
+                    .map(|start| start..start + length)
+                    .collect()
+            }
+            Self::Prefix(literal) | Self::Exact(literal) if !literal.bytes.is_empty() && self.is_match(subject) => {
+                once(0..literal.bytes.len()).collect()
+            }
+            Self::Suffix(literal) if !literal.bytes.is_empty() && self.is_match(subject) => {
+                once(subject.len() - literal.bytes.len()..subject.len()).collect()
+            }
+            // Highlight the dot too, as the pattern `\.rs$` did.
+            Self::Extension(literal) if self.is_match(subject) => {
+                once(subject.len() - literal.bytes.len() - 1..subject.len()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Anchor {
+    Anywhere,
+    Start,
+    End,
+    Whole,
+}
+
+/// Fixed strings searched together with one Aho-Corasick automaton.
+struct LiteralSet {
+    automaton: AhoCorasick,
+    /// Pattern index and placement of each string in the automaton.
+    members: Box<[(usize, Anchor)]>,
+}
+
+impl LiteralSet {
+    fn new(members: Vec<(Vec<u8>, usize, Anchor)>, ignore_case: bool) -> Result<Self, ScanError> {
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(ignore_case)
+            .build(members.iter().map(|(needle, _, _)| needle))
+            .map_err(|error| ScanError::Other(format!("too many patterns: {error}")))?;
+        Ok(Self {
+            automaton,
+            members: members.into_iter().map(|(_, index, anchor)| (index, anchor)).collect(),
+        })
+    }
+
+    /// Indices of the patterns found in `subject`, unordered and possibly repeated.
+    fn matches<'a>(&'a self, subject: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        self.automaton
+            .find_overlapping_iter(subject)
+            .filter_map(move |found| {
+                let (index, anchor) = self.members[found.pattern().as_usize()];
+                let placed = match anchor {
+                    Anchor::Anywhere => true,
+                    Anchor::Start => found.start() == 0,
+                    Anchor::End => found.end() == subject.len(),
+                    Anchor::Whole => found.start() == 0 && found.end() == subject.len(),
+                };
+                placed.then_some(index)
+            })
+    }
+}
+
+/// Patterns combined with OR and tested in one pass: fixed strings through Aho-Corasick,
+/// everything else through a single `RegexSet`.
+struct AnyOf {
+    literals: Box<[LiteralSet]>,
+    regexes: Option<RegexSet>,
+    /// Pattern index of each member of `regexes`.
+    regex_members: Box<[usize]>,
+    sources: Box<[Source]>,
+    /// Every pattern on its own, for [`Matcher::find_spans`]. Literals are kept from the start,
+    /// regexes are only compiled the first time they matched something that gets highlighted.
+    patterns: Box<[OnceLock<Option<Pattern>>]>,
+}
+
+impl AnyOf {
+    fn new(sources: Vec<Source>) -> Result<Self, ScanError> {
+        let mut sensitive = Vec::new();
+        let mut insensitive = Vec::new();
+        let mut regex_sources = Vec::new();
+        let mut regex_members = Vec::new();
+        let mut patterns = Vec::with_capacity(sources.len());
+        for (index, (source, case_insensitive)) in sources.iter().enumerate() {
+            let literal = Pattern::literal(source, *case_insensitive);
+            match literal.as_ref().and_then(Pattern::literal_parts) {
+                Some((needle, anchor, true)) => insensitive.push((needle, index, anchor)),
+                Some((needle, anchor, false)) => sensitive.push((needle, index, anchor)),
+                None => {
+                    regex_sources.push((source.clone(), *case_insensitive));
+                    regex_members.push(index);
+                }
+            }
+            patterns.push(literal.map_or_else(OnceLock::new, |literal| OnceLock::from(Some(literal))));
+        }
+
+        let literals = [(sensitive, false), (insensitive, true)]
+            .into_iter()
+            .filter(|(members, _)| !members.is_empty())
+            .map(|(members, ignore_case)| LiteralSet::new(members, ignore_case))
+            .collect::<Result<_, _>>()?;
+        let regexes = if regex_sources.is_empty() {
+            None
+        } else {
+            Some(build_set(&regex_sources)?)
+        };
+        Ok(Self {
+            literals,
+            regexes,
+            regex_members: regex_members.into(),
+            sources: sources.into(),
+            patterns: patterns.into(),
+        })
+    }
+
+    /// The pattern at `index` on its own, compiled on first use. `None` if it fails to compile
+    /// alone, which the set it already compiled in makes unlikely.
+    fn pattern(&self, index: usize) -> Option<&Pattern> {
+        self.patterns[index]
+            .get_or_init(|| {
+                let (source, case_insensitive) = &self.sources[index];
+                Pattern::new(source, *case_insensitive).ok()
+            })
+            .as_ref()
+    }
+
+    fn is_match(&self, subject: &[u8]) -> bool {
+        self.literals
+            .iter()
+            .any(|literals| literals.matches(subject).next().is_some())
+            || self.regexes.as_ref().is_some_and(|set| set.is_match(subject))
+    }
+
+    /// Indices of every pattern matching `subject`, unordered and possibly repeated.
+    fn matches<'a>(&'a self, subject: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let regex_matches = self
+            .regexes
+            .as_ref()
+            .map(|set| set.matches(subject))
+            .into_iter()
+            .flat_map(|matches| matches.into_iter().map(|member| self.regex_members[member]));
+        self.literals
+            .iter()
+            .flat_map(move |literals| literals.matches(subject))
+            .chain(regex_matches)
+    }
+}
+
+/// Which of the included patterns a path has to match.
+enum Include {
+    Everything,
+    One(Pattern),
+    Any(AnyOf),
+    All(Box<[Pattern]>),
+}
+
+/// Everything a path is tested against: the search patterns combined with OR (or AND when
+/// [`SearchConfig::match_all`] is set), minus anything matching an excluded pattern.
+///
+/// Each pattern gets its own smart case decision, and globs are converted when
+/// [`SearchConfig::use_glob`] is set. Globs are matched against the full path, the other
+/// patterns against whatever the caller passes to `is_match`, the filename or the full path.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{CaseMode, Matcher, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let mut search_config = SearchConfig::new(r"\.rs$", ".", true, CaseMode::Smart, 4, false, false, None, false, false);
+///     search_config.extra_patterns.push(r"\.toml$".into());
+///     search_config.exclude_patterns.push("^build".into());
+///
+///     let matcher = Matcher::new(&search_config)?;
+///     assert!(matcher.is_match(b"lib.rs"));
+///     assert!(matcher.is_match(b"Cargo.toml"));
+///     assert!(!matcher.is_match(b"build.rs"));
+///     assert!(!matcher.is_match(b"README.md"));
+///     Ok(())
+/// }
+/// ```
+pub struct Matcher {
+    include: Include,
+    /// Which included patterns only match directories, empty when none do.
+    dir_only: Box<[bool]>,
+    exclude: Option<RegexSet>,
+    /// Excluded patterns that only apply to directories.
+    exclude_dirs: Option<RegexSet>,
+    filter: Option<Expr>,
+    sniffer: Option<Sniffer>,
+    elf: Option<ElfFilter>,
+    contains: Option<ContentMatcher>,
+    /// Set in [`SearchConfig::match_dir`] mode, where `include` is left matching everything.
+    subtrees: Option<Arc<Subtrees>>,
+    parent: Option<Pattern>,
+    /// Whether directory names are matched by their full path, as globs are.
+    full_path: bool,
+    normalize: Option<Normalization>,
+}
+
+/// Directories whose name matched in [`SearchConfig::match_dir`] mode, along with every
+/// directory below them.
+///
+/// The walker's `filter_entry` marks subtrees as it descends: a directory is inside when its
+/// name matches or its parent is inside, so each directory name is matched once. Files outside
+/// are dropped there too, so the workers only see files that are inside. A mark is kept in
+/// `pending` until the thread that reads the directory takes it, and then stays with that
+/// thread while it goes through the entries. Directories that are never read, such as empty
+/// ones, keep their mark until the walk is over.
+pub struct Subtrees {
+    names: Matcher,
+    /// Marked directories that have not been read yet, in shards so threads rarely wait.
+    pending: Box<[Mutex<HashSet<BoxBytes>>]>,
+    hasher: RandomState,
+    /// Tells the per-thread marks of different matchers apart.
+    id: u64,
+}
+
+/// Number of [`Subtrees::pending`] shards.
+const PENDING_SHARDS: usize = 64;
+
+static NEXT_SUBTREES_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The [`Subtrees::id`], path and mark of the directory this thread is reading.
+    static CURRENT_DIR: RefCell<Option<(u64, BoxBytes, bool)>> = const { RefCell::new(None) };
+}
+
+impl Subtrees {
+    fn new(names: Matcher) -> Self {
+        Self {
+            names,
+            pending: (0..PENDING_SHARDS).map(|_| Mutex::default()).collect(),
+            hasher: RandomState::new(),
+            id: NEXT_SUBTREES_ID.fetch_add(1, AtomicOrdering::Relaxed),
+        }
+    }
+
+    fn shard(&self, path: &[u8]) -> &Mutex<HashSet<BoxBytes>> {
+        // The remainder is below `PENDING_SHARDS`, so it fits in a `usize`.
+        #[allow(clippy::cast_possible_truncation)]
+        &self.pending[(self.hasher.hash_one(path) % PENDING_SHARDS as u64) as usize]
+    }
+
+    /// Whether the directory at `path` is marked, taking its mark out of `pending` the first
+    /// time this thread asks.
+    fn is_marked(&self, path: &[u8]) -> bool {
+        let current = CURRENT_DIR.with_borrow(|current| {
+            current
+                .as_ref()
+                .filter(|(id, current_path, _)| *id == self.id && **current_path == *path)
+                .map(|(_, _, marked)| *marked)
+        });
+        current.unwrap_or_else(|| {
+            let marked = self.shard(path).lock().is_ok_and(|mut pending| pending.remove(path));
+            CURRENT_DIR.set(Some((self.id, path.into(), marked)));
+            marked
+        })
+    }
+
+    /// The walker's `filter_entry`: marks `entry` if it is a directory below the root that
+    /// matches or sits in a marked one, and drops files outside marked directories.
+    pub fn filter(&self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return true;
+        }
+        let parent_is_marked = entry
+            .path()
+            .parent()
+            .is_some_and(|parent| self.is_marked(parent.as_os_str().as_true_bytes()));
+        if !entry.file_type().is_some_and(|filetype| filetype.is_dir()) {
+            return parent_is_marked;
+        }
+        let path = entry.as_true_bytes();
+        let subject = if self.names.full_path { path } else { entry.filename_bytes() };
+        if parent_is_marked || self.names.is_match(subject) {
+            if let Ok(mut pending) = self.shard(path).lock() {
+                pending.insert(path.into());
+            }
+        }
+        true
+    }
+
+    /// Whether `entry` is a marked directory or a file the walker let through.
+    fn contains(&self, entry: &DirEntry) -> bool {
+        entry.depth() > 0
+            && (!entry.file_type().is_some_and(|filetype| filetype.is_dir()) || self.is_marked(entry.as_true_bytes()))
+    }
+}
+
+/// A regex source and whether it ignores case.
+type Source = (String, bool);
+
+/// Prepares one pattern from the config, returning the regex source, whether it ignores case
+/// and whether it only matches directories.
+fn prepare(search_config: &SearchConfig, pattern: &str) -> Result<(Source, bool), ScanError> {
+    let escape = if search_config.use_glob { globset::escape } else { regex::escape };
+    let pattern = &*search_config
+        .normalize
+        .map_or(Cow::Borrowed(pattern), |form| form.apply_pattern(pattern, escape));
+    let case_insensitive = search_config.case_mode.is_insensitive(pattern);
+    if search_config.use_glob {
+        let (source, dir_only) = glob_regex(pattern, &search_config.root, case_insensitive)?;
+        Ok(((source, case_insensitive), dir_only))
+    } else {
+        Ok(((pattern.into(), case_insensitive), false))
+    }
+}
+
+/// Splits prepared patterns into the regex sources and their directory only flags.
+fn prepare_all<'a>(
+    search_config: &SearchConfig,
+    patterns: impl Iterator<Item = &'a String>,
+) -> Result<(Vec<Source>, Vec<bool>), ScanError> {
+    let prepared = patterns
+        .map(|pattern| prepare(search_config, pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(prepared.into_iter().unzip())
+}
+
+fn build_optional_set(patterns: &[Source]) -> Result<Option<RegexSet>, ScanError> {
+    if patterns.is_empty() {
+        Ok(None)
+    } else {
+        build_set(patterns).map(Some)
+    }
+}
+
+fn build_set(patterns: &[(String, bool)]) -> Result<RegexSet, ScanError> {
+    RegexSetBuilder::new(patterns.iter().map(|(pattern, case_insensitive)| {
+        if *case_insensitive {
+            format!("(?i:{pattern})")
+        } else {
+            format!("(?:{pattern})")
+        }
+    }))
+    .build()
+    .map_err(ScanError::Regex)
+}
+
+impl Matcher {
+    /// Compiles the patterns of `search_config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ScanError` if any pattern fails to compile.
+    pub fn new(search_config: &SearchConfig) -> Result<Self, ScanError> {
+        let full_path = search_config.use_glob || search_config.full_path;
+        if search_config.match_dir {
+            let names = Self::new(&SearchConfig {
+                match_dir: false,
+                exclude_patterns: Vec::new(),
+                filter: None,
+                parent_pattern: None,
+                contains: None,
+                content_kind: None,
+                mime_types: Vec::new(),
+                elf: None,
+                ..search_config.clone()
+            })?;
+            let subtrees = Subtrees::new(names);
+            return Self::new(&SearchConfig {
+                pattern: DOT_PATTERN.into(),
+                extra_patterns: Vec::new(),
+                match_dir: false,
+                ..search_config.clone()
+            })
+            .map(|matcher| Self {
+                subtrees: Some(Arc::new(subtrees)),
+                ..matcher
+            });
+        }
+
+        let (included, dir_only) = prepare_all(
+            search_config,
+            once(&search_config.pattern)
+                .chain(&search_config.extra_patterns)
+                .filter(|pattern| !(search_config.extra_patterns.is_empty() && *pattern == DOT_PATTERN)),
+        )?;
+        let compile = |patterns: &[(String, bool)]| -> Result<Box<[Pattern]>, ScanError> {
+            patterns
+                .iter()
+                .map(|(pattern, case_insensitive)| Pattern::new(pattern, *case_insensitive))
+                .collect()
+        };
+
+        let include = match included.as_slice() {
+            [] => Include::Everything,
+            [(pattern, case_insensitive)] => Include::One(Pattern::new(pattern, *case_insensitive)?),
+            _ if search_config.match_all => Include::All(compile(&included)?),
+            _ => Include::Any(AnyOf::new(included)?),
+        };
+
+        let (excluded, exclude_dir_only) = prepare_all(search_config, search_config.exclude_patterns.iter())?;
+        let (excluded_dirs, excluded): (Vec<_>, Vec<_>) = excluded
+            .into_iter()
+            .zip(exclude_dir_only)
+            .partition(|(_, dir_only)| *dir_only);
+        let strip = |patterns: Vec<(Source, bool)>| -> Vec<Source> {
+            patterns.into_iter().map(|(pattern, _)| pattern).collect()
+        };
+        let parent = search_config
+            .parent_pattern
+            .as_deref()
+            .map(|pattern| {
+                let ((source, case_insensitive), _) = prepare(search_config, pattern)?;
+                Pattern::new(&source, case_insensitive)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            include,
+            dir_only: if dir_only.contains(&true) { dir_only.into() } else { Box::default() },
+            exclude: build_optional_set(&strip(excluded))?,
+            exclude_dirs: build_optional_set(&strip(excluded_dirs))?,
+            filter: search_config.filter.clone(),
+            sniffer: Sniffer::new(search_config.content_kind, &search_config.mime_types),
+            elf: search_config.elf.clone(),
+            contains: search_config
+                .contains
+                .as_deref()
+                .map(|pattern| ContentMatcher::new(pattern, search_config.case_mode, search_config.max_filesize))
+                .transpose()?,
+            subtrees: None,
+            parent,
+            full_path,
+            normalize: search_config.normalize,
+        })
+    }
+
+    /// The subtrees the walker marks in [`SearchConfig::match_dir`] mode.
+    pub(crate) fn subtrees(&self) -> Option<Arc<Subtrees>> {
+        self.subtrees.clone()
+    }
+
+    /// Whether every path matches, so the matcher can be skipped entirely.
+    #[must_use]
+    pub const fn matches_everything(&self) -> bool {
+        matches!(self.include, Include::Everything)
+            && self.exclude.is_none()
+            && self.exclude_dirs.is_none()
+            && self.filter.is_none()
+            && self.sniffer.is_none()
+            && self.elf.is_none()
+            && self.contains.is_none()
+            && self.subtrees.is_none()
+            && self.parent.is_none()
+    }
+
+    /// Whether `entry` passes the [`SearchConfig::filter`] expression, if there is one.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn accepts(&self, entry: &DirEntry) -> bool {
+        self.filter.as_ref().map_or(true, |filter| filter.matches(entry))
+    }
+
+    /// Whether `subject` matches the included patterns and none of the excluded ones.
+    ///
+    /// Globs ending in `/` are treated as if `subject` were a directory, use
+    /// [`Matcher::is_entry_match`] when the entry is at hand.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn is_match(&self, subject: &[u8]) -> bool {
+        let subject = &*self.normalized(subject);
+        self.includes(subject, true) && !self.excludes(subject, true)
+    }
+
+    /// `subject` in the [`SearchConfig::normalize`] form, borrowed when nothing changes.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn normalized<'a>(&self, subject: &'a [u8]) -> Cow<'a, [u8]> {
+        self.normalize
+            .map_or(Cow::Borrowed(subject), |form| form.apply_bytes(subject))
+    }
+
+    /// [`Matcher::is_match`] and [`Matcher::accepts`] together, with globs ending in `/` only
+    /// matching when `entry` is a directory. Also checks [`SearchConfig::match_dir`] and
+    /// [`SearchConfig::parent_pattern`], which need the entry. Subtrees are marked by the walks
+    /// of this crate, so for entries from another walk `match_dir` lets every file through.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    #[must_use]
+    pub fn is_entry_match(&self, subject: &[u8], entry: &DirEntry) -> bool {
+        let is_dir = entry.file_type().is_some_and(|filetype| filetype.is_dir());
+        let subject = &*self.normalized(subject);
+        self.includes(subject, is_dir)
+            && !self.excludes(subject, is_dir)
+            && self.parent_matches(entry)
+            && self.subtrees.as_ref().map_or(true, |subtrees| subtrees.contains(entry))
+            && self.accepts(entry)
+            && self.content_matches(entry)
+    }
+
+    /// Runs the filters that read the file, opening it once for all of them.
+    fn content_matches(&self, entry: &DirEntry) -> bool {
+        if self.sniffer.is_none() && self.elf.is_none() && self.contains.is_none() {
+            return true;
+        }
+        if !entry.file_type().is_some_and(|filetype| filetype.is_file()) {
+            return false;
+        }
+        FileHead::open(entry.path()).is_ok_and(|file| {
+            self.sniffer.as_ref().map_or(true, |sniffer| sniffer.is_head_match(&file))
+                && self.elf.as_ref().map_or(true, |elf| elf.is_head_match(&file))
+                && self.contains.as_ref().map_or(true, |contains| contains.is_head_match(&file))
+        })
+    }
+
+    fn parent_matches(&self, entry: &DirEntry) -> bool {
+        self.parent.as_ref().map_or(true, |pattern| {
+            entry.path().parent().is_some_and(|parent| {
+                let subject = if self.full_path {
+                    parent.as_os_str()
+                } else {
+                    parent.file_name().unwrap_or_default()
+                };
+                pattern.is_match(&self.normalized(subject.as_true_bytes()))
+            })
+        })
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn includes(&self, subject: &[u8], is_dir: bool) -> bool {
+        let allowed = |index: usize| is_dir || !self.dir_only.get(index).is_some_and(|dir_only| *dir_only);
+        match &self.include {
+            Include::Everything => true,
+            Include::One(pattern) => allowed(0) && pattern.is_match(subject),
+            Include::Any(any) if is_dir || self.dir_only.is_empty() => any.is_match(subject),
+            Include::Any(any) => any.matches(subject).any(allowed),
+            Include::All(patterns) => patterns
+                .iter()
+                .enumerate()
+                .all(|(index, pattern)| allowed(index) && pattern.is_match(subject)),
+        }
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn excludes(&self, subject: &[u8], is_dir: bool) -> bool {
+        self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(subject))
+            || (is_dir && self.exclude_dirs.as_ref().is_some_and(|exclude| exclude.is_match(subject)))
+    }
+
+    /// Index of the first included pattern matching `subject`, counting `pattern` as 0 and then
+    /// `extra_patterns` in order. `None` when nothing matched or every path matches.
+    ///
+    /// Excluded patterns are not checked, use [`Matcher::is_match`] for that.
+    #[must_use]
+    pub fn matched_pattern(&self, subject: &[u8]) -> Option<usize> {
+        let subject = &*self.normalized(subject);
+        match &self.include {
+            Include::Everything => None,
+            Include::One(pattern) => pattern.is_match(subject).then_some(0),
+            Include::Any(any) => any.matches(subject).min(),
+            Include::All(patterns) => patterns
+                .iter()
+                .all(|pattern| pattern.is_match(subject))
+                .then_some(0),
+        }
+    }
+
+    /// Byte ranges of `subject` matched by any included pattern, sorted and merged, with
+    /// empty matches left out. Empty as well when normalization changed `subject`.
+    #[must_use]
+    pub fn find_spans(&self, subject: &[u8]) -> Vec<Range<usize>> {
+        // Ranges in a normalized name don't line up with the raw bytes that get printed.
+        if matches!(self.normalized(subject), Cow::Owned(_)) {
+            return Vec::new();
+        }
+        let mut spans = match &self.include {
+            Include::Everything => return Vec::new(),
+            Include::One(pattern) => return pattern.find_spans(subject),
+            Include::Any(any) => {
+                let mut matched: Vec<usize> = any.matches(subject).collect();
+                matched.sort_unstable();
+                matched.dedup();
+                matched
+                    .into_iter()
+                    .flat_map(|index| any.pattern(index).map(|pattern| pattern.find_spans(subject)).unwrap_or_default())
+                    .collect::<Vec<_>>()
+            }
+            Include::All(patterns) => patterns
+                .iter()
+                .flat_map(|pattern| pattern.find_spans(subject))
+                .collect(),
+        };
+
+        spans.sort_unstable_by_key(|span| span.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(spans.len());
+        for span in spans {
+            match merged.last_mut() {
+                Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+                _ => merged.push(span),
+            }
+        }
+        merged
+    }
+}
+
+/// Reads patterns from a file, one per line. Blank lines and lines starting with `#` are
+/// skipped, use `\#` for a regex that starts with a hash.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the file can't be read.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{load_patterns, ScanError};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let file = std::env::temp_dir().join("scanit-doc-patterns.txt");
+///     std::fs::write(&file, "# licence files\nLICENSE\n\nCOPYING\n")?;
+///
+///     assert_eq!(load_patterns(&file)?, ["LICENSE", "COPYING"]);
+///     Ok(())
+/// }
+/// ```
+pub fn load_patterns(path: impl AsRef<Path>) -> Result<Vec<String>, ScanError> {
+    Ok(read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(Into::into)
+        .collect())
+}
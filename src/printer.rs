@@ -1,12 +1,19 @@
 
-use memchr::memrchr;
-use scanit::{BoxBytes, Receiver, ScanError};
+use scanit::{extension_bytes, is_separator, read_users, BoxBytes, ContentMatch, DirCount, DirUsage, FuzzyMatch, PathMatch, RankBy, RankedPath, ScanError, ScanStats, SummaryBy, SummaryGroup};
 
-use std::io::{Write,BufWriter,stdout};
+use crate::hyperlink::Hyperlinker;
+use crate::ls_colors::{classify, Kind, LsColors};
+use crate::theme::Theme;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::io::{Write,BufWriter,IsTerminal,stdout,stderr};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 
 const NEWLINE:&[u8]=b"\n";
 const RESET : &[u8] = b"\x1b[0m";
+const DIM : &[u8] = b"\x1b[2m";
+const HIGHLIGHT : &[u8] = b"\x1b[1;31m";
 const COLOUR_RS : &[u8] = b"\x1b[38;2;200;60;0m";
 const COLOUR_PY : &[u8] = b"\x1b[38;2;0;200;200m";
 const COLOUR_CPP : &[u8] = b"\x1b[38;2;0;100;200m";
@@ -53,7 +60,7 @@ const COLOUR_SQL: &[u8] = b"\x1b[38;2;100;100;100m";
 #[allow(clippy::inline_always)]
 #[inline(always)]
 fn extension_colour(bytes: &[u8]) ->&[u8] {
-    memrchr(b'.', bytes).map_or(RESET, |pos| match &bytes[pos + 1..] {
+    extension_bytes(bytes).map_or(RESET, |extension| match extension {
         b"rs" => COLOUR_RS,
         b"py" => COLOUR_PY,
         b"cpp" => COLOUR_CPP,
@@ -97,50 +104,637 @@ fn extension_colour(bytes: &[u8]) ->&[u8] {
 
 
 
+/// Picks colours from the user's theme first, then `LS_COLORS` when it is set, and otherwise
+/// from the built in extension table.
+pub struct Colouriser {
+    theme: Option<Theme>,
+    ls_colors: Option<LsColors>,
+}
+
+impl Colouriser {
+    pub fn from_env() -> Self {
+        Self {
+            theme: Theme::load(),
+            ls_colors: LsColors::from_env(),
+        }
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn colour<'a>(&'a self, path: &'a [u8]) -> &'a [u8] {
+        self.theme
+            .as_ref()
+            .and_then(|theme| Self::theme_colour(theme, path))
+            .unwrap_or_else(|| {
+                self.ls_colors
+                    .as_ref()
+                    .map_or_else(|| extension_colour(path), |ls_colors| ls_colors.colour(path))
+            })
+    }
+
+    fn theme_colour<'a>(theme: &'a Theme, path: &[u8]) -> Option<&'a [u8]> {
+        if theme.needs_metadata() {
+            let by_type = match classify(path) {
+                Kind::Link | Kind::Orphan => theme.symlink.as_deref(),
+                Kind::Dir | Kind::Sticky | Kind::OtherWritable | Kind::StickyOtherWritable => {
+                    theme.directory.as_deref()
+                }
+                Kind::Exec | Kind::Setuid | Kind::Setgid => theme.executable.as_deref(),
+                _ => None,
+            };
+            if by_type.is_some() {
+                return by_type;
+            }
+        }
+        extension_bytes(path)
+            .and_then(|extension| theme.extensions.get(extension))
+            .map(|escape| &**escape)
+    }
+
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn reset(&self) -> &[u8] {
+        self.ls_colors.as_ref().map_or(RESET, LsColors::reset)
+    }
+
+    /// Writes one coloured path, dimming the parent directories if the theme asks for it and
+    /// highlighting the byte ranges in `spans`.
+    #[allow(clippy::inline_always)]
+    #[inline(always)]
+    fn write_path(
+        &self,
+        buffer: &mut impl Write,
+        path: &[u8],
+        spans: &[Range<usize>],
+    ) -> Result<(), ScanError> {
+        let colour = self.colour(path);
+        let name_start = if self.theme.as_ref().is_some_and(|theme| theme.dim_parent) {
+            path.iter()
+                .rposition(|&byte| is_separator(byte))
+                .map_or(0, |split| split + 1)
+        } else {
+            0
+        };
+
+        if spans.is_empty() && name_start == 0 {
+            buffer.write_all(colour)?;
+            buffer.write_all(path)?;
+            buffer.write_all(self.reset())?;
+            return Ok(());
+        }
+
+        let mut start = 0;
+        let mut spans = spans.iter().peekable();
+        while start < path.len() {
+            let (style, end) = match spans.peek() {
+                Some(span) if span.start <= start => {
+                    let end = span.end.max(start + 1);
+                    spans.next();
+                    (HIGHLIGHT, end)
+                }
+                next => {
+                    let span_start = next.map_or(path.len(), |span| span.start);
+                    if start < name_start {
+                        (DIM, name_start.min(span_start))
+                    } else {
+                        (colour, span_start)
+                    }
+                }
+            };
+            let end = end.min(path.len());
+            if start > 0 {
+                buffer.write_all(self.reset())?;
+            }
+            buffer.write_all(style)?;
+            buffer.write_all(&path[start..end])?;
+            start = end;
+        }
+        buffer.write_all(self.reset())?;
+        Ok(())
+    }
+}
+
+/// Like [`write_paths_coloured`], but also highlights the part of each path the pattern matched.
+pub fn write_matches_coloured(
+    matches: impl IntoIterator<Item = PathMatch>,
+    limit: Option<usize>,
+    colours: &Colouriser,
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
+    let interactive = stdout().is_terminal();
+    let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
+
+    for found in matches.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
+        if let Some(links) = links {
+            links.open(&mut buffer, &found.path)?;
+        }
+        colours.write_path(&mut buffer, &found.path, &found.spans)?;
+        if links.is_some() {
+            Hyperlinker::close(&mut buffer)?;
+        }
+        buffer.write_all(NEWLINE)?;
+        if interactive {
+            buffer.flush()?;
+        }
+    }
+
+    buffer.flush()?;
+    Ok(written)
+}
+
+/// Prints `pattern<TAB>path` for each match, naming the pattern that matched it.
+pub fn write_pattern_matches(
+    matches: impl IntoIterator<Item = PathMatch>,
+    limit: Option<usize>,
+    names: &[&str],
+) -> Result<usize, ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
+
+    for found in matches.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
+        let name = found.pattern.and_then(|index| names.get(index)).unwrap_or(&"");
+        buffer.write_all(name.as_bytes())?;
+        buffer.write_all(b"\t")?;
+        buffer.write_all(&found.path)?;
+        buffer.write_all(NEWLINE)?;
+    }
+
+    buffer.flush()?;
+    Ok(written)
+}
+
+/// Writes `"path":` and the path as a JSON string. A path that isn't valid UTF-8 shows U+FFFD
+/// there and is also written exactly as `"path_bytes":[...]`, an array of its byte values.
+fn write_json_path(buffer: &mut impl Write, path: &[u8]) -> Result<(), ScanError> {
+    buffer.write_all(b"\"path\":")?;
+    write_json_string(buffer, path)?;
+    if std::str::from_utf8(path).is_err() {
+        buffer.write_all(b",\"path_bytes\":[")?;
+        for (index, byte) in path.iter().enumerate() {
+            if index > 0 {
+                buffer.write_all(b",")?;
+            }
+            write!(buffer, "{byte}")?;
+        }
+        buffer.write_all(b"]")?;
+    }
+    Ok(())
+}
+
+/// Writes `bytes` as a JSON string, with invalid UTF-8 replaced by U+FFFD.
+fn write_json_string(buffer: &mut impl Write, bytes: &[u8]) -> Result<(), ScanError> {
+    buffer.write_all(b"\"")?;
+    for &byte in String::from_utf8_lossy(bytes).as_bytes() {
+        match byte {
+            b'"' => buffer.write_all(b"\\\"")?,
+            b'\\' => buffer.write_all(b"\\\\")?,
+            b'\n' => buffer.write_all(b"\\n")?,
+            b'\t' => buffer.write_all(b"\\t")?,
+            0..=0x1f => write!(buffer, "\\u{byte:04x}")?,
+            _ => buffer.write_all(&[byte])?,
+        }
+    }
+    buffer.write_all(b"\"")?;
+    Ok(())
+}
+
+/// Writes one `{"path": ...}` object per line.
+pub fn write_paths_json(paths: impl IntoIterator<Item = BoxBytes>, limit: Option<usize>) -> Result<usize, ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
+
+    for path in paths.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
+        buffer.write_all(b"{")?;
+        write_json_path(&mut buffer, &path)?;
+        buffer.write_all(b"}\n")?;
+    }
+
+    buffer.flush()?;
+    Ok(written)
+}
+
+/// Writes one `{"path": ..., "score": ...}` object per line, best match first.
+pub fn write_fuzzy_json(matches: &[FuzzyMatch]) -> Result<usize, ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+
+    for found in matches {
+        buffer.write_all(b"{")?;
+        write_json_path(&mut buffer, &found.path)?;
+        writeln!(buffer, ",\"score\":{}}}", found.score)?;
+    }
+
+    buffer.flush()?;
+    Ok(matches.len())
+}
+
+/// Writes `path:line` for each file, or `{"path": ..., "line": ...}` objects with `json`.
+pub fn write_content_matches(
+    matches: impl IntoIterator<Item = ContentMatch>,
+    limit: Option<usize>,
+    json: bool,
+) -> Result<usize, ScanError> {
+    let interactive = stdout().is_terminal();
+    let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
+
+    for found in matches.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
+        if json {
+            buffer.write_all(b"{")?;
+            write_json_path(&mut buffer, &found.path)?;
+            writeln!(buffer, ",\"line\":{}}}", found.line)?;
+        } else {
+            buffer.write_all(&found.path)?;
+            writeln!(buffer, ":{}", found.line)?;
+        }
+        if interactive {
+            buffer.flush()?;
+        }
+    }
+
+    buffer.flush()?;
+    Ok(written)
+}
+
 #[allow(clippy::inline_always)]
 #[inline(always)]
 pub fn write_paths_plain(
-    paths: &Receiver<BoxBytes>,
+    paths: impl IntoIterator<Item = BoxBytes>,
     limit: Option<usize>,
-) -> Result<(), ScanError> {
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
 
+    // Flush every line on a terminal so results show up as the walk finds them.
+    let interactive = stdout().is_terminal();
     let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
 
-    for path in paths.iter().take(limit.unwrap_or(usize::MAX)) {
+    for path in paths.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
         //unsafe {
-            buffer.write_all(&path)?;
+            if let Some(links) = links {
+                links.open(&mut buffer, &path)?;
+                buffer.write_all(&path)?;
+                Hyperlinker::close(&mut buffer)?;
+            } else {
+                buffer.write_all(&path)?;
+            }
             buffer.write_all(NEWLINE)?;
+            if interactive {
+                buffer.flush()?;
+            }
 
     }
 
     buffer.flush()?;
-    Ok(())
+    Ok(written)
 }
 
 #[allow(clippy::inline_always)]
 #[inline(always)]
 pub fn write_paths_coloured(
-    paths: &Receiver<BoxBytes>,
+    paths: impl IntoIterator<Item = BoxBytes>,
     limit: Option<usize>,
-) -> Result<(), ScanError> {
+    colours: &Colouriser,
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
 
+    let interactive = stdout().is_terminal();
     let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
 
-    for path in paths.iter().take(limit.unwrap_or(usize::MAX)) {
+    for path in paths.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
 
 
-            buffer.write_all(extension_colour(&path))?;
-            buffer.write_all(&path)?;
+            if let Some(links) = links {
+                links.open(&mut buffer, &path)?;
+            }
+            colours.write_path(&mut buffer, &path, &[])?;
+            if links.is_some() {
+                Hyperlinker::close(&mut buffer)?;
+            }
             buffer.write_all(NEWLINE)?;
+            if interactive {
+                buffer.flush()?;
+            }
 
     }
 
 
     buffer.flush()?;
 
+    Ok(written)
+}
+
+
+
+pub fn write_stats(stats: &ScanStats) -> Result<(), ScanError> {
+    let mut out = stderr().lock();
+
+    writeln!(out, "files visited:        {} ({} matched)", stats.files_visited, stats.files_matched)?;
+    writeln!(out, "directories visited:  {} ({} matched)", stats.dirs_visited, stats.dirs_matched)?;
+    writeln!(out, "bytes matched:        {}", stats.bytes_matched)?;
+    writeln!(out, "elapsed:              {:.3}s", stats.elapsed.as_secs_f64())?;
+    writeln!(out, "entries per second:   {:.0}", stats.entries_per_second())?;
+    writeln!(out, "system paths skipped: {}", stats.skipped_paths)?;
+    writeln!(out, "errors:               {}", stats.error_count())?;
+    for (kind, count) in &stats.errors {
+        writeln!(out, "  {kind}: {count}")?;
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Maps the uids used as `--summary-by owner` keys to user names.
+fn user_names() -> HashMap<Vec<u8>, Vec<u8>> {
+    read_users()
+        .into_iter()
+        .map(|user| (user.uid.to_string().into_bytes(), user.name.into_vec()))
+        .collect()
+}
+
+fn csv_field(field: &[u8]) -> Vec<u8> {
+    if !field.iter().any(|byte| matches!(byte, b',' | b'"' | b'\n' | b'\r')) {
+        return field.to_vec();
+    }
+    let mut quoted = Vec::with_capacity(field.len() + 2);
+    quoted.push(b'"');
+    for &byte in field {
+        if byte == b'"' {
+            quoted.push(b'"');
+        }
+        quoted.push(byte);
+    }
+    quoted.push(b'"');
+    quoted
+}
+
+pub fn write_summary(groups: &[SummaryGroup], by: SummaryBy, csv: bool) -> Result<(), ScanError> {
+    let heading = match by {
+        SummaryBy::Extension => "ext",
+        SummaryBy::Directory => "dir",
+        SummaryBy::Owner => "owner",
+        SummaryBy::Depth => "depth",
+    };
+    let names = if by == SummaryBy::Owner { user_names() } else { HashMap::new() };
+    let display_key = |key: &[u8]| -> Vec<u8> {
+        if key.is_empty() && by == SummaryBy::Extension {
+            return b"(none)".to_vec();
+        }
+        names.get(key).map_or_else(|| key.to_vec(), Clone::clone)
+    };
+
+    let mut buffer = BufWriter::new(stdout().lock());
+
+    if csv {
+        writeln!(buffer, "{heading},count,bytes")?;
+        for group in groups {
+            buffer.write_all(&csv_field(&display_key(&group.key)))?;
+            writeln!(buffer, ",{},{}", group.count, group.bytes)?;
+        }
+    } else {
+        writeln!(buffer, "{:>10}  {:>10}  {heading}", "count", "size")?;
+        for group in groups {
+            write!(buffer, "{:>10}  {:>10}  ", group.count, human_size(group.bytes))?;
+            buffer.write_all(&display_key(&group.key))?;
+            buffer.write_all(NEWLINE)?;
+        }
+    }
+
+    buffer.flush()?;
+    Ok(())
+}
+
+pub fn write_dir_counts(counts: &[DirCount], csv: bool) -> Result<(), ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+
+    if csv {
+        writeln!(buffer, "dir,count")?;
+        for dir in counts {
+            buffer.write_all(&csv_field(&dir.path))?;
+            writeln!(buffer, ",{}", dir.count)?;
+        }
+    } else {
+        for dir in counts {
+            write!(buffer, "{:>10}  ", dir.count)?;
+            buffer.write_all(&dir.path)?;
+            buffer.write_all(NEWLINE)?;
+        }
+    }
+
+    buffer.flush()?;
     Ok(())
 }
 
+pub fn write_disk_usage(usage: &[DirUsage]) -> Result<(), ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+
+    writeln!(buffer, "{:>10}  {:>10}  path", "allocated", "apparent")?;
+    for dir in usage {
+        write!(buffer, "{:>10}  {:>10}  ", human_size(dir.allocated), human_size(dir.apparent))?;
+        buffer.write_all(&dir.path)?;
+        buffer.write_all(NEWLINE)?;
+    }
+
+    buffer.flush()?;
+    Ok(())
+}
+
+/// Writes the files of a `--largest`, `--smallest`, `--newest` or `--oldest` search, best first,
+/// each after its size or, when ranked by time, when it was last modified (UTC). With `json`
+/// each line is `{"path": ..., "size": ..., "modified": ...}`, where `modified` is in seconds
+/// since the Unix epoch, or `null` when the platform doesn't record it.
+pub fn write_ranked(
+    ranked: &[RankedPath],
+    by: RankBy,
+    limit: Option<usize>,
+    json: bool,
+    colours: Option<&Colouriser>,
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
+    let mut buffer = BufWriter::new(stdout().lock());
+    let mut written = 0;
+
+    for found in ranked.iter().take(limit.unwrap_or(usize::MAX)) {
+        written += 1;
+        if json {
+            buffer.write_all(b"{")?;
+            write_json_path(&mut buffer, &found.path)?;
+            write!(buffer, ",\"size\":{},\"modified\":", found.size)?;
+            match found.modified {
+                Some(modified) => writeln!(buffer, "{}}}", unix_seconds(modified))?,
+                None => writeln!(buffer, "null}}")?,
+            }
+            continue;
+        }
+
+        match by {
+            RankBy::Largest | RankBy::Smallest => write!(buffer, "{:>10}  ", human_size(found.size))?,
+            RankBy::Newest | RankBy::Oldest => {
+                write!(buffer, "{:>19}  ", found.modified.map_or_else(|| "-".into(), utc_timestamp))?;
+            }
+        }
+        if let Some(links) = links {
+            links.open(&mut buffer, &found.path)?;
+        }
+        if let Some(colours) = colours {
+            colours.write_path(&mut buffer, &found.path, &[])?;
+        } else {
+            buffer.write_all(&found.path)?;
+        }
+        if links.is_some() {
+            Hyperlinker::close(&mut buffer)?;
+        }
+        buffer.write_all(NEWLINE)?;
+    }
+
+    buffer.flush()?;
+    Ok(written)
+}
+
+/// Seconds since the Unix epoch, negative for earlier times.
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or_else(
+        |before| -i64::try_from(before.duration().as_secs()).unwrap_or(i64::MAX),
+        |after| i64::try_from(after.as_secs()).unwrap_or(i64::MAX),
+    )
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM:SS` in UTC.
+fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let (days, second_of_day) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // The civil date of a day count since 1970-01-01, in the proleptic Gregorian calendar.
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60
+    )
+}
+
+#[derive(Default)]
+struct TreeNode {
+    children: BTreeMap<BoxBytes, TreeNode>,
+    /// The full path, set on nodes that were matched themselves.
+    path: Option<BoxBytes>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, relative: &[u8], path: &BoxBytes) {
+        let mut node = self;
+        for component in relative
+            .split(|&byte| is_separator(byte))
+            .filter(|component| !component.is_empty())
+        {
+            node = node.children.entry(component.into()).or_default();
+        }
+        node.path = Some(path.clone());
+    }
+
+    fn write(
+        &self,
+        buffer: &mut impl Write,
+        prefix: &mut Vec<u8>,
+        colours: Option<&Colouriser>,
+        links: Option<&Hyperlinker>,
+    ) -> Result<(), ScanError> {
+        let mut remaining = self.children.len();
+        for (name, child) in &self.children {
+            remaining -= 1;
+            let last = remaining == 0;
+            buffer.write_all(prefix)?;
+            buffer.write_all(if last { TREE_LAST } else { TREE_BRANCH })?;
+            let link = links.zip(child.path.as_ref());
+            if let Some((links, path)) = link {
+                links.open(buffer, path)?;
+            }
+            if let (Some(colours), Some(path)) = (colours, &child.path) {
+                buffer.write_all(colours.colour(path))?;
+                buffer.write_all(name)?;
+                buffer.write_all(colours.reset())?;
+            } else {
+                buffer.write_all(name)?;
+            }
+            if link.is_some() {
+                Hyperlinker::close(buffer)?;
+            }
+            buffer.write_all(NEWLINE)?;
+
+            if !child.children.is_empty() {
+                let depth = prefix.len();
+                prefix.extend_from_slice(if last { TREE_SPACE } else { TREE_PIPE });
+                child.write(buffer, prefix, colours, links)?;
+                prefix.truncate(depth);
+            }
+        }
+        Ok(())
+    }
+}
+
+const TREE_BRANCH: &[u8] = "├── ".as_bytes();
+const TREE_LAST: &[u8] = "└── ".as_bytes();
+const TREE_PIPE: &[u8] = "│   ".as_bytes();
+const TREE_SPACE: &[u8] = b"    ";
+
+/// Buffers every path, then prints them as a sorted tree under `root`, showing only the
+/// directories that lead to a match. Returns how many paths were printed.
+pub fn write_paths_tree(
+    paths: impl IntoIterator<Item = BoxBytes>,
+    root: &str,
+    limit: Option<usize>,
+    colours: Option<&Colouriser>,
+    links: Option<&Hyperlinker>,
+) -> Result<usize, ScanError> {
+    let root = root.as_bytes();
+    let mut tree = TreeNode::default();
+
+    let mut written = 0;
+    for path in paths.into_iter().take(limit.unwrap_or(usize::MAX)) {
+        tree.insert(path.strip_prefix(root).unwrap_or(&path), &path);
+        written += 1;
+    }
+
+    let mut buffer = BufWriter::new(stdout().lock());
+    buffer.write_all(root)?;
+    buffer.write_all(NEWLINE)?;
+    tree.write(&mut buffer, &mut Vec::new(), colours, links)?;
+    buffer.flush()?;
+    Ok(written)
+}
+
 
 
 //commented out assembly implementation i did for lols
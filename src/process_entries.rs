@@ -1,5 +1,7 @@
-use crate::{BoxBytes, Regex,Sender};
-use ignore::{DirEntry, WalkState};
+use crate::Matcher;
+use ignore::DirEntry;
+use memchr::memrchr;
+use std::ops::Range;
 //use os_str_bytes::OsStrBytes;
 use std::ffi::OsStr;
 #[cfg(unix)]
@@ -64,39 +66,85 @@ impl FileNameBytes for DirEntry {
     }
 }
 
-#[doc(hidden)]
+/// Returns the extension of the last component of `path`, without the dot.
+///
+/// Uses `memrchr` to find the final dot, so this is cheap enough to call per printed path.
 #[allow(clippy::inline_always)]
 #[inline(always)]
-pub fn process_entry_fullpath(
-    entry_path: &DirEntry,
-    re: Option<&Regex>,
-    tx: &Sender<BoxBytes>,
-) -> WalkState {
-    let filename = entry_path.as_true_bytes();
-    if re.map_or(true, |search| search.is_match(filename)) {
-        tx.send(filename.into())
-            .map_or(WalkState::Skip, |()| WalkState::Continue)
+#[must_use]
+pub fn extension_bytes(path: &[u8]) -> Option<&[u8]> {
+    let extension = &path[memrchr(b'.', path)? + 1..];
+    if extension.iter().any(|&byte| is_separator(byte)) {
+        None
     } else {
-        WalkState::Continue
+        Some(extension)
+    }
+}
+
+/// Whether `byte` separates path components: `/`, or on Windows also `\`.
+#[allow(clippy::inline_always)]
+#[inline(always)]
+#[must_use]
+pub const fn is_separator(byte: u8) -> bool {
+    #[cfg(windows)]
+    {
+        byte == b'/' || byte == b'\\'
+    }
+    #[cfg(not(windows))]
+    {
+        byte == b'/'
     }
 }
 
 #[doc(hidden)]
 #[allow(clippy::inline_always)]
 #[inline(always)]
-pub fn process_entry_shortpath(
-    entry_path: &DirEntry,
-    re: Option<&Regex>,
-    tx: &Sender<BoxBytes>,
-) -> WalkState {
-    if re.map_or(true, |search| search.is_match(entry_path.filename_bytes())) {
-        tx.send(entry_path.as_true_bytes().into())
-            .map_or(WalkState::Skip, |()| WalkState::Continue)
+pub fn is_match_fullpath(entry_path: &DirEntry, re: Option<&Matcher>) -> bool {
+    re.map_or(true, |search| search.is_entry_match(entry_path.as_true_bytes(), entry_path))
+}
+
+#[doc(hidden)]
+#[allow(clippy::inline_always)]
+#[inline(always)]
+pub fn is_match_shortpath(entry_path: &DirEntry, re: Option<&Matcher>) -> bool {
+    re.map_or(true, |search| search.is_entry_match(entry_path.filename_bytes(), entry_path))
+}
+
+/// The part of the entry's path that is matched, the filename unless `full_path` is set,
+/// along with where it starts in the full path.
+#[must_use]
+pub fn match_subject(entry_path: &DirEntry, full_path: bool) -> (&[u8], usize) {
+    let path = entry_path.as_true_bytes();
+    if full_path {
+        (path, 0)
     } else {
-        WalkState::Continue
+        // A root given as `sub/` keeps its trailing separator in the path but not the filename.
+        let filename = entry_path.filename_bytes();
+        let end = path.iter().rposition(|&byte| !is_separator(byte)).map_or(0, |last| last + 1);
+        (filename, end.saturating_sub(filename.len()))
     }
 }
 
+/// The byte ranges of the entry's full path matched by `re`, searching only the filename
+/// unless `full_path` is set.
+#[must_use]
+pub fn match_spans(entry_path: &DirEntry, re: Option<&Matcher>, full_path: bool) -> Box<[Range<usize>]> {
+    let Some(re) = re else {
+        return Box::default();
+    };
+    let (subject, offset) = match_subject(entry_path, full_path);
+    re.find_spans(subject)
+        .into_iter()
+        .map(|found| found.start + offset..found.end + offset)
+        .collect()
+}
+
+/// Index of the first pattern that matched the entry, see [`Matcher::matched_pattern`].
+#[must_use]
+pub fn matched_pattern(entry_path: &DirEntry, re: Option<&Matcher>, full_path: bool) -> Option<usize> {
+    re?.matched_pattern(match_subject(entry_path, full_path).0)
+}
+
 /*
 
 
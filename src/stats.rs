@@ -0,0 +1,101 @@
+use ignore::Error as WalkError;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Counters collected while walking, returned by [`crate::find_files_with_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    pub files_visited: u64,
+    pub dirs_visited: u64,
+    pub files_matched: u64,
+    pub dirs_matched: u64,
+    /// Total size in bytes of the matched files.
+    pub bytes_matched: u64,
+    /// Paths left out by the system path filter (`AVOID`).
+    pub skipped_paths: u64,
+    /// Walk errors keyed by a short description of their kind, e.g. "permission denied".
+    pub errors: BTreeMap<String, u64>,
+    pub elapsed: Duration,
+}
+
+impl ScanStats {
+    /// Total number of entries the walker visited.
+    #[must_use]
+    pub const fn entries_visited(&self) -> u64 {
+        self.files_visited + self.dirs_visited
+    }
+
+    /// Total number of entries that matched the search.
+    #[must_use]
+    pub const fn entries_matched(&self) -> u64 {
+        self.files_matched + self.dirs_matched
+    }
+
+    /// Total number of errors across every kind.
+    #[must_use]
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Visited entries per second of wall-clock time.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn entries_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            0.0
+        } else {
+            self.entries_visited() as f64 / seconds
+        }
+    }
+
+    pub(crate) fn record_error(&mut self, error: &WalkError) {
+        *self.errors.entry(error_kind(error)).or_insert(0) += 1;
+    }
+
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.files_visited += other.files_visited;
+        self.dirs_visited += other.dirs_visited;
+        self.files_matched += other.files_matched;
+        self.dirs_matched += other.dirs_matched;
+        self.bytes_matched += other.bytes_matched;
+        self.skipped_paths += other.skipped_paths;
+        for (kind, count) in &other.errors {
+            *self.errors.entry(kind.clone()).or_insert(0) += count;
+        }
+    }
+}
+
+fn error_kind(error: &WalkError) -> String {
+    match error {
+        WalkError::Loop { .. } => "filesystem loop".into(),
+        WalkError::WithPath { err, .. } | WalkError::WithDepth { err, .. } => error_kind(err),
+        _ => error
+            .io_error()
+            .map_or_else(|| "other".into(), |io_error| io_error.kind().to_string()),
+    }
+}
+
+/// Counters owned by one worker thread, merged into `merged` when the worker finishes.
+pub struct LocalStats<'a> {
+    pub stats: ScanStats,
+    merged: &'a Mutex<ScanStats>,
+}
+
+impl<'a> LocalStats<'a> {
+    pub fn new(merged: &'a Mutex<ScanStats>) -> Self {
+        Self {
+            stats: ScanStats::default(),
+            merged,
+        }
+    }
+}
+
+impl Drop for LocalStats<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            merged.merge(&self.stats);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{build_walker, compile_pattern, entry_matcher, extension_bytes, AsBytes, BoxBytes, ScanError, SearchConfig};
+use ignore::{DirEntry, WalkState};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::sync::Mutex;
+
+/// How matched files are grouped by [`summarise`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryBy {
+    /// File extension, empty for files without one.
+    Extension,
+    /// Parent directory.
+    Directory,
+    /// Numeric id of the owning user (always "unknown" off unix).
+    Owner,
+    /// Depth below the search root.
+    Depth,
+}
+
+/// One group of a summary report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryGroup {
+    pub key: BoxBytes,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+type Groups = HashMap<BoxBytes, (u64, u64)>;
+
+impl SummaryBy {
+    fn key(self, entry: &DirEntry, metadata: &Metadata) -> BoxBytes {
+        match self {
+            Self::Extension => extension_bytes(entry.as_true_bytes()).unwrap_or_default().into(),
+            Self::Directory => entry
+                .path()
+                .parent()
+                .map_or_else(Default::default, |parent| parent.as_os_str().as_true_bytes().into()),
+            Self::Owner => owner_key(metadata),
+            Self::Depth => entry.depth().to_string().into_bytes().into(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn owner_key(metadata: &Metadata) -> BoxBytes {
+    use std::os::unix::fs::MetadataExt;
+    metadata.uid().to_string().into_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn owner_key(_metadata: &Metadata) -> BoxBytes {
+    (*b"unknown").into()
+}
+
+/// Groups owned by one worker thread, merged into `merged` when the worker finishes.
+struct LocalGroups<'a> {
+    groups: Groups,
+    merged: &'a Mutex<Groups>,
+}
+
+impl Drop for LocalGroups<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            for (key, (count, bytes)) in self.groups.drain() {
+                let group = merged.entry(key).or_insert((0, 0));
+                group.0 += count;
+                group.1 += bytes;
+            }
+        }
+    }
+}
+
+/// Groups the matched files by `by`, returning the count and total size of each group,
+/// largest total size first.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{summarise, CaseMode, ScanError, SearchConfig, SummaryBy};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(".", "src", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     for group in summarise(&search_config, SummaryBy::Extension)? {
+///         println!("{:?}: {} files, {} bytes", &*group.key, group.count, group.bytes);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn summarise(search_config: &SearchConfig, by: SummaryBy) -> Result<Vec<SummaryGroup>, ScanError> {
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let merged: Mutex<Groups> = Mutex::new(HashMap::new());
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let mut local = LocalGroups {
+            groups: HashMap::new(),
+            merged: &merged,
+        };
+        let re = re.as_ref();
+        Box::new(move |entry| {
+            entry.map_or(WalkState::Continue, |entry_path| {
+                if entry_path
+                    .file_type()
+                    .map_or(true, |filetype| filetype.is_dir())
+                    || !is_match(&entry_path, re)
+                {
+                    return WalkState::Continue;
+                }
+
+                if let Ok(metadata) = entry_path.metadata() {
+                    let group = local
+                        .groups
+                        .entry(by.key(&entry_path, &metadata))
+                        .or_insert((0, 0));
+                    group.0 += 1;
+                    group.1 += metadata.len();
+                }
+                WalkState::Continue
+            })
+        })
+    });
+
+    let mut groups: Vec<SummaryGroup> = merged
+        .into_inner()
+        .map_err(|_| ScanError::Other("a worker thread panicked".into()))?
+        .into_iter()
+        .map(|(key, (count, bytes))| SummaryGroup { key, count, bytes })
+        .collect();
+    groups.sort_unstable_by(|left, right| {
+        right
+            .bytes
+            .cmp(&left.bytes)
+            .then_with(|| left.key.cmp(&right.key))
+    });
+    Ok(groups)
+}
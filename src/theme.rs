@@ -0,0 +1,257 @@
+use scanit::BoxBytes;
+use std::collections::HashMap;
+use std::env::var_os;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// How many colours the terminal can show, theme colours are degraded to fit.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ColourDepth {
+    Ansi16,
+    Ansi256,
+    TrueColour,
+}
+
+impl ColourDepth {
+    fn detect() -> Self {
+        if var_os("COLORTERM").is_some_and(|term| term == "truecolor" || term == "24bit") {
+            Self::TrueColour
+        } else if var_os("TERM").is_some_and(|term| term.to_string_lossy().contains("256")) {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// A colour as written in a theme file.
+#[derive(Clone, Copy)]
+enum Colour {
+    /// `"#rrggbb"`
+    Rgb(u8, u8, u8),
+    /// `0` to `255`
+    Indexed(u8),
+    /// `"red"`, `"bright-blue"` and so on, stored as the SGR foreground code.
+    Named(u8),
+}
+
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// The standard xterm values of the 16 basic colours, used to find the nearest match.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl Colour {
+    fn parse(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Integer(index) => u8::try_from(*index)
+                .map(Self::Indexed)
+                .map_err(|_| format!("colour index {index} is not between 0 and 255")),
+            Value::String(text) => Self::parse_str(text),
+            other => Err(format!("expected a colour, found a {}", other.type_str())),
+        }
+    }
+
+    fn parse_str(text: &str) -> Result<Self, String> {
+        let lower = text.trim().to_ascii_lowercase();
+        if let Some(hex) = lower.strip_prefix('#') {
+            let channel = |range: std::ops::Range<usize>| {
+                hex.get(range)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            };
+            return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+                (6, Some(red), Some(green), Some(blue)) => Ok(Self::Rgb(red, green, blue)),
+                _ => Err(format!("{text:?} is not a #rrggbb colour")),
+            };
+        }
+        if let Ok(index) = lower.parse::<u8>() {
+            return Ok(Self::Indexed(index));
+        }
+
+        let (bright, name) = lower
+            .strip_prefix("bright-")
+            .or_else(|| lower.strip_prefix("bright_"))
+            .map_or((false, lower.as_str()), |name| (true, name));
+        NAMES
+            .iter()
+            .position(|known| *known == name)
+            .and_then(|position| u8::try_from(position).ok())
+            .map(|position| Self::Named(if bright { 90 } else { 30 } + position))
+            .ok_or_else(|| format!("unknown colour {text:?}"))
+    }
+
+    fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(red, green, blue) => (red, green, blue),
+            Self::Named(code) => BASIC_RGB[usize::from(code % 10) + if code >= 90 { 8 } else { 0 }],
+            Self::Indexed(index) if index < 16 => BASIC_RGB[usize::from(index)],
+            Self::Indexed(index) if index >= 232 => {
+                let grey = 8 + (index - 232) * 10;
+                (grey, grey, grey)
+            }
+            Self::Indexed(index) => {
+                let cube = usize::from(index - 16);
+                (CUBE_LEVELS[cube / 36], CUBE_LEVELS[cube / 6 % 6], CUBE_LEVELS[cube % 6])
+            }
+        }
+    }
+
+    fn to_indexed(self) -> u8 {
+        match self {
+            Self::Indexed(index) => index,
+            Self::Named(code) => code % 10 + if code >= 90 { 8 } else { 0 },
+            Self::Rgb(red, green, blue) => {
+                let level = |channel: u8| {
+                    CUBE_LEVELS
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, level)| level.abs_diff(channel))
+                        .map_or(0, |(position, _)| position as u8)
+                };
+                if red == green && green == blue {
+                    if red < 8 {
+                        16
+                    } else if red > 238 {
+                        231
+                    } else {
+                        232 + (red - 8) / 10
+                    }
+                } else {
+                    16 + 36 * level(red) + 6 * level(green) + level(blue)
+                }
+            }
+        }
+    }
+
+    fn to_named(self) -> u8 {
+        if let Self::Named(code) = self {
+            return code;
+        }
+        let (red, green, blue) = self.to_rgb();
+        let distance = |(other_red, other_green, other_blue): &(u8, u8, u8)| {
+            let square = |left: u8, right: u8| u32::from(left.abs_diff(right)).pow(2);
+            square(red, *other_red) + square(green, *other_green) + square(blue, *other_blue)
+        };
+        let nearest = BASIC_RGB
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, rgb)| distance(rgb))
+            .map_or(0, |(position, _)| position as u8);
+        if nearest < 8 {
+            30 + nearest
+        } else {
+            82 + nearest
+        }
+    }
+
+    /// Turns the colour into a foreground escape sequence the terminal can show.
+    fn escape(self, depth: ColourDepth) -> BoxBytes {
+        let code = match (self, depth) {
+            (Self::Rgb(red, green, blue), ColourDepth::TrueColour) => format!("38;2;{red};{green};{blue}"),
+            (Self::Named(code), _) => code.to_string(),
+            (_, ColourDepth::Ansi16) => self.to_named().to_string(),
+            _ => format!("38;5;{}", self.to_indexed()),
+        };
+        format!("\x1b[{code}m").into_bytes().into()
+    }
+}
+
+/// A compiled user theme, every colour is already an escape sequence.
+pub struct Theme {
+    /// Extension (without the dot) to escape sequence.
+    pub extensions: HashMap<BoxBytes, BoxBytes>,
+    pub directory: Option<BoxBytes>,
+    pub symlink: Option<BoxBytes>,
+    pub executable: Option<BoxBytes>,
+    /// Print the parent path dimmed so the file name stands out.
+    pub dim_parent: bool,
+}
+
+impl Theme {
+    /// `$XDG_CONFIG_HOME/scanit/theme.toml`, `~/.config/scanit/theme.toml`, or `%APPDATA%\scanit\theme.toml` on Windows.
+    fn path() -> Option<PathBuf> {
+        let config_dir = if cfg!(windows) {
+            var_os("APPDATA").map(PathBuf::from)
+        } else {
+            var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        config_dir.map(|dir| dir.join("scanit").join("theme.toml"))
+    }
+
+    /// Loads the user's theme, if there is one. A broken theme is reported and ignored.
+    pub fn load() -> Option<Self> {
+        let path = Self::path()?;
+        let text = std::fs::read_to_string(&path).ok()?;
+        Self::parse(&text, ColourDepth::detect())
+            .map_err(|error| eprintln!("Ignoring theme {}: {error}", path.display()))
+            .ok()
+    }
+
+    fn parse(text: &str, depth: ColourDepth) -> Result<Self, String> {
+        let table: Table = text.parse().map_err(|error| format!("{error}"))?;
+        let colour = |key: &str| -> Result<Option<BoxBytes>, String> {
+            table
+                .get(key)
+                .map(|value| {
+                    Colour::parse(value)
+                        .map(|colour| colour.escape(depth))
+                        .map_err(|error| format!("{key}: {error}"))
+                })
+                .transpose()
+        };
+
+        let mut extensions = HashMap::new();
+        if let Some(value) = table.get("extensions") {
+            let Value::Table(entries) = value else {
+                return Err("extensions must be a table".into());
+            };
+            for (extension, value) in entries {
+                let escape = Colour::parse(value)
+                    .map_err(|error| format!("extensions.{extension}: {error}"))?
+                    .escape(depth);
+                let extension = extension.strip_prefix('.').unwrap_or(extension);
+                extensions.insert(extension.as_bytes().into(), escape);
+            }
+        }
+
+        Ok(Self {
+            extensions,
+            directory: colour("directory")?,
+            symlink: colour("symlink")?,
+            executable: colour("executable")?,
+            dim_parent: match table.get("dim_parent") {
+                None => false,
+                Some(Value::Boolean(dim)) => *dim,
+                Some(_) => return Err("dim_parent must be true or false".into()),
+            },
+        })
+    }
+
+    /// Whether colouring needs to look at each path's file type.
+    pub const fn needs_metadata(&self) -> bool {
+        self.directory.is_some() || self.symlink.is_some() || self.executable.is_some()
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{build_walker, compile_pattern, entry_matcher, AsBytes, BoxBytes, ScanError, SearchConfig};
+use ignore::WalkState;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The property files are ranked by in a top-k search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankBy {
+    Largest,
+    Smallest,
+    Newest,
+    Oldest,
+}
+
+/// A file kept by a top-k search, along with the values it was ranked on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedPath {
+    pub path: BoxBytes,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// A heap slot ordered by its rank key, ties broken by path so merging is deterministic.
+struct HeapEntry {
+    key: i128,
+    ranked: RankedPath,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| other.ranked.path.cmp(&self.ranked.path))
+    }
+}
+
+impl RankBy {
+    /// Maps a file onto a single key where a larger key always ranks higher.
+    fn key(self, size: u64, modified: Option<SystemTime>) -> i128 {
+        let nanos = || {
+            modified.map_or(i128::MIN, |time| {
+                time.duration_since(UNIX_EPOCH).map_or_else(
+                    |before| -i128::try_from(before.duration().as_nanos()).unwrap_or(i128::MAX),
+                    |after| i128::try_from(after.as_nanos()).unwrap_or(i128::MAX),
+                )
+            })
+        };
+        match self {
+            Self::Largest => i128::from(size),
+            Self::Smallest => -i128::from(size),
+            Self::Newest => nanos(),
+            Self::Oldest => nanos().checked_neg().unwrap_or(i128::MIN),
+        }
+    }
+}
+
+/// A bounded min-heap owned by one worker thread, merged into `merged` when the worker finishes.
+struct LocalHeap<'a> {
+    heap: BinaryHeap<Reverse<HeapEntry>>,
+    limit: usize,
+    merged: &'a Mutex<Vec<HeapEntry>>,
+}
+
+impl LocalHeap<'_> {
+    /// Keeps the file if it beats the worst one kept, comparing `(key, path)` like [`HeapEntry`]
+    /// so that equal keys give the same result on every run.
+    #[inline]
+    fn offer(&mut self, key: i128, path: &[u8], ranked: impl FnOnce() -> RankedPath) {
+        if self.heap.len() < self.limit {
+            self.heap.push(Reverse(HeapEntry { key, ranked: ranked() }));
+        } else if self
+            .heap
+            .peek()
+            .is_some_and(|Reverse(worst)| key.cmp(&worst.key).then_with(|| (*worst.ranked.path).cmp(path)).is_gt())
+        {
+            self.heap.pop();
+            self.heap.push(Reverse(HeapEntry { key, ranked: ranked() }));
+        }
+    }
+}
+
+impl Drop for LocalHeap<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut merged) = self.merged.lock() {
+            merged.extend(self.heap.drain().map(|Reverse(entry)| entry));
+        }
+    }
+}
+
+/// Finds the `limit` best files under the search configuration, ranked by `rank`.
+///
+/// Each worker thread keeps its own heap of at most `limit` entries and the heaps are merged
+/// once the walk finishes, so memory stays proportional to `limit * thread_count` regardless
+/// of how many files are visited. Only regular files are considered.
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+///
+/// # Examples
+/// ```rust
+/// use scanit::{find_top_k, CaseMode, RankBy, ScanError, SearchConfig};
+///
+/// fn main() -> Result<(), ScanError> {
+///     let search_config = SearchConfig::new(r"\.rs$", ".", true, CaseMode::Smart, 4, false, false, None, false, false);
+///
+///     for ranked in find_top_k(&search_config, RankBy::Largest, 3)? {
+///         println!("{} {:?}", ranked.size, &*ranked.path);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn find_top_k(
+    search_config: &SearchConfig,
+    rank: RankBy,
+    limit: usize,
+) -> Result<Vec<RankedPath>, ScanError> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let re = compile_pattern(search_config)?;
+    let is_match = entry_matcher(search_config);
+    let merged: Mutex<Vec<HeapEntry>> = Mutex::new(Vec::new());
+
+    build_walker(search_config, re.as_ref(), None).run(|| {
+        let mut local = LocalHeap {
+            heap: BinaryHeap::with_capacity(limit + 1),
+            limit,
+            merged: &merged,
+        };
+        let re = re.as_ref();
+        Box::new(move |entry| {
+            entry.map_or(WalkState::Continue, |entry_path| {
+                if !entry_path
+                    .file_type()
+                    .is_some_and(|filetype| filetype.is_file())
+                    || !is_match(&entry_path, re)
+                {
+                    return WalkState::Continue;
+                }
+
+                if let Ok(metadata) = entry_path.metadata() {
+                    let modified = metadata.modified().ok();
+                    let size = metadata.len();
+                    let path = entry_path.as_true_bytes();
+                    local.offer(rank.key(size, modified), path, || RankedPath {
+                        path: path.into(),
+                        size,
+                        modified,
+                    });
+                }
+                WalkState::Continue
+            })
+        })
+    });
+
+    let mut best = merged
+        .into_inner()
+        .map_err(|_| ScanError::Other("a worker thread panicked".into()))?;
+    best.sort_unstable_by(|left, right| right.cmp(left));
+    best.truncate(limit);
+    Ok(best.into_iter().map(|entry| entry.ranked).collect())
+}
+
+/// Finds the `limit` largest files. See [`find_top_k`].
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+#[inline]
+pub fn find_largest(search_config: &SearchConfig, limit: usize) -> Result<Vec<RankedPath>, ScanError> {
+    find_top_k(search_config, RankBy::Largest, limit)
+}
+
+/// Finds the `limit` smallest files. See [`find_top_k`].
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+#[inline]
+pub fn find_smallest(search_config: &SearchConfig, limit: usize) -> Result<Vec<RankedPath>, ScanError> {
+    find_top_k(search_config, RankBy::Smallest, limit)
+}
+
+/// Finds the `limit` most recently modified files. See [`find_top_k`].
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+#[inline]
+pub fn find_newest(search_config: &SearchConfig, limit: usize) -> Result<Vec<RankedPath>, ScanError> {
+    find_top_k(search_config, RankBy::Newest, limit)
+}
+
+/// Finds the `limit` least recently modified files. See [`find_top_k`].
+///
+/// # Errors
+///
+/// Returns a `ScanError` if the search pattern fails to compile.
+#[inline]
+pub fn find_oldest(search_config: &SearchConfig, limit: usize) -> Result<Vec<RankedPath>, ScanError> {
+    find_top_k(search_config, RankBy::Oldest, limit)
+}
@@ -0,0 +1,45 @@
+use crate::BoxBytes;
+
+/// A user listed in `/etc/passwd`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: BoxBytes,
+    pub uid: u32,
+}
+
+/// Reads the users in `/etc/passwd`, skipping malformed lines. Empty when the file can't be
+/// read, and always on platforms without one.
+///
+/// # Examples
+/// ```rust
+/// use scanit::read_users;
+///
+/// if let Some(root) = read_users().iter().find(|user| &*user.name == b"root") {
+///     assert_eq!(root.uid, 0);
+/// }
+/// ```
+#[must_use]
+pub fn read_users() -> Vec<User> {
+    #[cfg(unix)]
+    {
+        std::fs::read("/etc/passwd").map_or_else(|_| Vec::new(), |passwd| parse_passwd(&passwd))
+    }
+    #[cfg(not(unix))]
+    {
+        Vec::new()
+    }
+}
+
+/// Parses `name:password:uid:...` lines.
+#[cfg(unix)]
+fn parse_passwd(passwd: &[u8]) -> Vec<User> {
+    passwd
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| {
+            let mut fields = line.split(|&byte| byte == b':');
+            let name = fields.next()?;
+            let uid = std::str::from_utf8(fields.nth(1)?).ok()?.parse().ok()?;
+            Some(User { name: name.into(), uid })
+        })
+        .collect()
+}